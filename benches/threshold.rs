@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+
+use color_space_threshold_helper::{enums::{ColorSpace, OutputMode, WhitePoint}, process::{convert_from_rgb_f32, ConversionLut, ImageToProc, ThreshParams, DEFAULT_THRESH_COLOR}};
+use image::{DynamicImage, RgbImage};
+use rayon::prelude::*;
+
+/// Roughly 50 megapixels, about the size of a large microscope scan
+const BENCH_SIZE: (u32, u32) = (8660, 5774);
+/// Small enough to run quickly when cargo test builds the benches
+const TEST_SIZE: (u32, u32) = (160, 90);
+
+/// Compares the parallel threshold engine, with and without a lookup
+/// table, against a simple per-pixel reference implementation, and checks
+/// every entry of each table against converting directly.
+/// Run with `cargo bench`.
+fn main() {
+    // cargo bench passes --bench, while cargo test just runs us as a smoke test
+    let (width, height) = match std::env::args().any(|a| a == "--bench") {
+        true => BENCH_SIZE,
+        false => TEST_SIZE,
+    };
+    println!("Generating {width}x{height} test image ({:.1} megapixels)", (width * height) as f64 / 1_000_000.);
+    let img = ImageToProc::from_image(DynamicImage::ImageRgb8(gradient_image(width, height)));
+
+    for color_space in [ColorSpace::RGB, ColorSpace::HSBorHSV, ColorSpace::LabCIE] {
+        let params = ThreshParams::from_depths(color_space, WhitePoint::D65, [(40.,200.,true), (20.,230.,true), (10.,250.,true)]);
+
+        let start = Instant::now();
+        let reference = img.threshold_img_reference(&params, DEFAULT_THRESH_COLOR).expect("Bench params are valid");
+        let reference_time = start.elapsed();
+
+        let start = Instant::now();
+        let parallel = img.threshold_img(params.clone(), DEFAULT_THRESH_COLOR).expect("Bench params are valid");
+        let parallel_time = start.elapsed();
+
+        let start = Instant::now();
+        let lut = ConversionLut::new(color_space, WhitePoint::D65);
+        let lut_build_time = start.elapsed();
+        let lut_mismatch = (0..1u32 << 24).into_par_iter()
+            .map(|i| [(i >> 16) as u8, (i >> 8) as u8, i as u8])
+            .find_any(|rgb| lut.convert(*rgb) != convert_from_rgb_f32(*rgb, color_space, WhitePoint::D65));
+        assert!(lut_mismatch.is_none(), "Lookup table differs from converting {:?} directly for {:?}", lut_mismatch, color_space);
+
+        let start = Instant::now();
+        let with_lut = img.threshold_img_with_lut(&params, DEFAULT_THRESH_COLOR, OutputMode::Overlay, &lut, |_| (), || false).expect("Bench params are valid");
+        let lut_time = start.elapsed();
+
+        assert!(reference == parallel, "Parallel engine output differs from reference output for {:?}", color_space);
+        assert!(Some(&reference) == with_lut.as_ref(), "Lookup table output differs from reference output for {:?}", color_space);
+        println!(
+            "{:?}: reference {}, parallel {}, speedup {:.1}x, lookup table {} (+{} to build)",
+            color_space,
+            format_time(reference_time),
+            format_time(parallel_time),
+            reference_time.as_secs_f64() / parallel_time.as_secs_f64().max(f64::EPSILON),
+            format_time(lut_time),
+            format_time(lut_build_time),
+        );
+    }//end benchmarking each color space
+}//end main method
+
+/// Makes an image with smoothly changing colors and some flat
+/// regions, so it resembles a real photo more than noise would.
+fn gradient_image(width: u32, height: u32) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        let r = (x * 255 / width.max(1)) as u8;
+        let g = (y * 255 / height.max(1)) as u8;
+        let b = (((x / 16) ^ (y / 16)) % 256) as u8;
+        image::Rgb([r, g, b])
+    })
+}//end gradient_image()
+
+fn format_time(time: Duration) -> String {
+    format!("{:.3}s", time.as_secs_f64())
+}//end format_time()
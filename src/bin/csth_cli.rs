@@ -0,0 +1,156 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use color_space_threshold_helper::{enums::{ColorSpace, OutputMode, WhitePoint}, preset::ThreshPreset, process::{ImageToProc, ThreshParams, ThreshRule, DEFAULT_THRESH_COLOR}};
+
+/// A color space, channel, and (min, max, pass) in native units
+type NativeRule = (ColorSpace, usize, (f32,f32,bool));
+
+const USAGE: &str = "Usage: csth_cli --input <path> --output <path> (--space <color space> | --preset <path>) [--d1 <min,max,pass|stop>]... [--d2 <min,max,pass|stop>]... [--d3 <min,max,pass|stop>]... [--expr <expression>] [--rule <color space,channel,min,max,pass|stop>]... [--white D50|D55|D65|A|F2|<x,y,z>] [--color <r,g,b>] [--mode overlay|mask|inverted-mask|transparent]";
+
+/// Headless entry point which runs the same thresholding as the gui,
+/// but takes every parameter from the command line.
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{USAGE}");
+        println!("Color spaces: {}", ColorSpace::get_variants().join(", "));
+        println!("Depths take min and max in the native units of each channel, such as \"--d1 200,260,pass\" for hue in degrees, or \"--d2 -20,30.5,pass\" for Lab a");
+        println!("Expressions combine channels in native units with and, or and not, such as \"hue in 20..40 and (saturation > 40 or lab.l > 50)\"");
+        println!("Rules need a channel of any color space to pass as well, with min and max in native units, such as \"Lab,0,40,100,pass\"");
+        return ExitCode::SUCCESS;
+    }//end if we should just print usage
+
+    match run(args) {
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        },
+        Ok(_) => ExitCode::SUCCESS,
+    }//end matching whether we could threshold the image
+}//end main method
+
+/// Parses the arguments, thresholds the input, and writes the output.
+/// Arguments are applied in order, so flags after --preset override its values.
+/// Giving a depth more than once adds more intervals to it, which are OR-ed,
+/// while every rule is AND-ed with the rest of the threshold.
+/// Depths and rules, including those from a preset, are kept in native units
+/// like expressions, and only converted to our [0,255] scale once the color
+/// space and white point are known.
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut input: Option<PathBuf> = None;
+    let mut output: Option<PathBuf> = None;
+    let mut color_space: Option<ColorSpace> = None;
+    // depth intervals are in native units, and converted with the rules
+    let mut intervals: [Vec<(f32,f32,bool)>; 3] = Default::default();
+    // whether the next interval for each depth replaces the ones we have
+    let mut replace_intervals = [true; 3];
+    let mut white_point = WhitePoint::D65;
+    let mut expression: Option<String> = None;
+    // rules are kept in native units until we know the white point
+    let mut native_rules: Vec<NativeRule> = Vec::new();
+    let mut thresh_color: (u8,u8,u8) = DEFAULT_THRESH_COLOR;
+    let mut mode = OutputMode::Overlay;
+
+    let mut args_iter = args.into_iter();
+    while let Some(flag) = args_iter.next() {
+        let value = args_iter.next().ok_or_else(|| format!("Missing value after {flag}"))?;
+        match flag.as_str() {
+            "--input" | "-i" => input = Some(PathBuf::from(value)),
+            "--output" | "-o" => output = Some(PathBuf::from(value)),
+            "--space" | "-s" => color_space = Some(
+                ColorSpace::from_str(&value).ok_or_else(|| format!("Unknown color space \"{value}\""))?
+            ),
+            "--preset" | "-p" => {
+                let preset = ThreshPreset::read_preset(&PathBuf::from(value))?;
+                let params = preset.params;
+                color_space = Some(params.color_space);
+                intervals = params.native_intervals();
+                replace_intervals = [true; 3];
+                white_point = params.white_point;
+                expression = params.expression;
+                native_rules = params.rules.iter()
+                    .map(|rule| (rule.color_space, rule.channel, rule.native_interval(params.white_point)))
+                    .collect();
+                thresh_color = preset.thresh_color;
+            },
+            "--white" | "-w" => white_point = WhitePoint::from_str(&value)
+                .ok_or_else(|| format!("Unknown white point \"{value}\", expected D50, D55, D65, A, F2 or X,Y,Z"))?,
+            "--d1" | "--d2" | "--d3" => {
+                let i = match flag.as_str() {"--d1" => 0, "--d2" => 1, _ => 2};
+                if replace_intervals[i] {intervals[i].clear();}
+                replace_intervals[i] = false;
+                intervals[i].push(parse_depth(&value)?);
+            },
+            "--expr" | "-e" => expression = Some(value),
+            "--rule" | "-r" => native_rules.push(parse_rule(&value)?),
+            "--color" | "-c" => thresh_color = parse_color(&value)?,
+            "--mode" | "-m" => mode = OutputMode::from_str(&value)
+                .ok_or_else(|| format!("Unknown output mode \"{value}\""))?,
+            _ => return Err(format!("Unknown argument \"{flag}\"")),
+        }//end matching flag
+    }//end looping over each flag and value
+
+    let input = input.ok_or("No --input path given")?;
+    let output = output.ok_or("No --output path given")?;
+    let color_space = color_space.ok_or("No --space or --preset given")?;
+
+    let rules = native_rules.into_iter()
+        .map(|(space, channel, interval)| ThreshRule::from_native(space, channel, white_point, interval))
+        .collect();
+    let params = ThreshParams {
+        expression,
+        rules,
+        ..ThreshParams::from_native_intervals(color_space, white_point, intervals)
+    };
+    params.to_expr().map_err(|err| format!("Couldn't read the threshold expression because: {err}"))?;
+
+    let img = ImageToProc::read_image(input.clone())
+        .map_err(|err| format!("Couldn't load image at {} because ImageError: {:?}", input.to_string_lossy(), err))?;
+    let th_img = img.threshold_img_with_progress(&params, thresh_color, mode, |_| (), || false)?
+        .ok_or("Thresholding was cancelled")?;
+    ImageToProc::save_image(&th_img, &output)
+        .map_err(|err| format!("Couldn't save image to {} because ImageError: {:?}", output.to_string_lossy(), err))?;
+    println!("Wrote thresholded image to {}", output.to_string_lossy());
+    Ok(())
+}//end run()
+
+/// Parses a min/max/pass triple such as `0,360,pass` or `40.5,200,stop`,
+/// with min and max in the native units of whichever channel it's for.
+fn parse_depth(value: &str) -> Result<(f32,f32,bool), String> {
+    let parts: Vec<&str> = value.split(',').map(|s| s.trim()).collect();
+    if parts.len() != 3 {return Err(format!("Expected min,max,pass|stop but got \"{value}\""));}
+    let min = parts[0].parse::<f32>().map_err(|_| format!("Invalid min \"{}\" in \"{value}\"", parts[0]))?;
+    let max = parts[1].parse::<f32>().map_err(|_| format!("Invalid max \"{}\" in \"{value}\"", parts[1]))?;
+    let pass = match parts[2].to_lowercase().as_str() {
+        "pass" | "true" => true,
+        "stop" | "false" => false,
+        other => return Err(format!("Expected pass or stop but got \"{other}\"")),
+    };
+    Ok((min,max,pass))
+}//end parse_depth()
+
+/// Parses a rule such as `Lab,0,40,100,pass`, with the channel counted
+/// from 0 and min and max in the native units of that channel.
+fn parse_rule(value: &str) -> Result<NativeRule, String> {
+    let (space, rest) = value.split_once(',').ok_or_else(|| format!("Expected color space,channel,min,max,pass|stop but got \"{value}\""))?;
+    let space = ColorSpace::from_str(space.trim()).ok_or_else(|| format!("Unknown color space \"{space}\" in \"{value}\""))?;
+    let (channel, depth) = rest.split_once(',').ok_or_else(|| format!("Expected color space,channel,min,max,pass|stop but got \"{value}\""))?;
+    let channel = match channel.trim().parse::<usize>() {
+        Ok(channel) if channel < 3 => channel,
+        _ => return Err(format!("Invalid channel \"{channel}\" in \"{value}\", expected 0, 1 or 2")),
+    };
+    Ok((space, channel, parse_depth(depth)?))
+}//end parse_rule()
+
+/// Parses an rgb color such as `255,0,0`.
+fn parse_color(value: &str) -> Result<(u8,u8,u8), String> {
+    let parts: Vec<u8> = value.split(',')
+        .map(|s| s.trim().parse::<u8>())
+        .collect::<Result<Vec<u8>,_>>()
+        .map_err(|_| format!("Invalid color \"{value}\""))?;
+    match parts[..] {
+        [r,g,b] => Ok((r,g,b)),
+        _ => Err(format!("Expected r,g,b but got \"{value}\"")),
+    }//end matching number of color components
+}//end parse_color()
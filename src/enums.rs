@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use serde::{Deserialize, Serialize};
+
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum InterfaceMessage {
@@ -8,6 +10,32 @@ pub enum InterfaceMessage {
     /// Contains an rbg color
     ThreshColor((u8,u8,u8)),
     DoThreshold,
+    /// Re-thresholds a downscaled preview after params change
+    PreviewThreshold,
+    /// Contains the id of a running threshold job and its percent done
+    ThresholdProgress(u64, u8),
+    /// Contains the id of a threshold job whose result is ready
+    ThresholdDone(u64),
+    /// Contains the path to save the last thresholded image to
+    SaveImage(PathBuf),
+    /// Contains the path to save the current threshold preset to
+    SavePreset(PathBuf),
+    /// Contains the path to load a threshold preset from
+    LoadPreset(PathBuf),
+    /// Copies the current threshold as ImageJ macro code
+    ExportImageJ,
+    /// Copies the current threshold as OpenCV inRange bounds
+    ExportOpenCV,
+    /// Fills the expression input with what the sliders stand for
+    ExpressionFromSliders,
+    /// Sets the sliders from the pixels in a region picked on the
+    /// displayed image, which has the (width, height) given
+    SamplePixels(SampleRegion, (u32,u32)),
+    /// Shows the pixel under the mouse in the inspector, with its
+    /// position on the displayed image, which has the (width, height) given
+    InspectPixel((u32,u32), (u32,u32)),
+    /// Recounts the histograms after the color space or white point changes
+    UpdateHistograms,
     Quit
 }//end enum InterfaceMessage
 
@@ -16,22 +44,57 @@ pub enum InterfaceMessage {
 /// In order to add a new ColorSpace, edit the following:
 /// - variants in ColorSpace
 /// - each implemented function of ColorSpace
-/// - process::convert_from_rgb()
-#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord,Hash)]
+/// - process::convert_to_internal()
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord,Hash,Serialize,Deserialize)]
 #[non_exhaustive]
 pub enum ColorSpace {
     RGB,
+    /// Hue, saturation and value each scaled to [0,255]
     HSBorHSV,
+    /// Hue, saturation and lightness each scaled to [0,255]
     HSL,
+    /// Hue, saturation and intensity each scaled to [0,255]
     HSI,
+    /// L scaled from [0,100], a and b from [-128,128] to [0,255]
     LabCIE,
+    /// JPEG-style YUV, with U and V offset by 128
     YUV,
-    // CMY,
-    // HunterLab,
-    // LCH,
-    // LUV,
-    // XYZ,
-    // YXY,
+    /// Cyan, magenta and yellow as 255 minus red, green and blue
+    CMY,
+    /// Hunter L scaled from [0,100] to [0,255], a from [-70,110]
+    /// and b from [-200,60] (the extents of sRGB) to [0,255]
+    HunterLab,
+    /// CIE L scaled from [0,100], chroma from [0,134] (the most
+    /// chroma in sRGB) and hue from [0,360] to [0,255]
+    LCH,
+    /// CIE L scaled from [0,100], u from [-84,176]
+    /// and v from [-135,108] (the extents of sRGB) to [0,255]
+    LUV,
+    /// CIE XYZ with each channel scaled so the white point is 255
+    XYZ,
+    /// Luminance Y and chromaticity x and y scaled from [0,1] to [0,255]
+    YXY,
+    /// BT.601 YCbCr with Y, Cb and Cr using the full [0,255] range
+    YCbCr601,
+    /// BT.601 YCbCr with Y in [16,235] and Cb and Cr in [16,240]
+    YCbCr601Studio,
+    /// BT.709 YCbCr with Y, Cb and Cr using the full [0,255] range
+    YCbCr709,
+    /// BT.709 YCbCr with Y in [16,235] and Cb and Cr in [16,240]
+    YCbCr709Studio,
+    /// BT.2020 YCbCr with Y, Cb and Cr using the full [0,255] range
+    YCbCr2020,
+    /// BT.2020 YCbCr with Y in [16,235] and Cb and Cr in [16,240]
+    YCbCr2020Studio,
+    /// NTSC YIQ, with Y scaled to [0,255] and I from [-0.5959,0.5959]
+    /// and Q from [-0.5227,0.5227] scaled to [0,255]
+    YIQ,
+    /// OKLab L scaled from [0,1], a from [-0.24,0.28]
+    /// and b from [-0.32,0.2] (the extents of sRGB) to [0,255]
+    OKLab,
+    /// OKLab L scaled from [0,1], chroma from [0,0.33] (the most
+    /// chroma in sRGB) and hue from [0,360] to [0,255]
+    OKLCh,
 }//end enum ColorSpace
 
 impl ColorSpace {
@@ -43,12 +106,21 @@ impl ColorSpace {
             "HSI" => Some(ColorSpace::HSI),
             "LabCIE" => Some(ColorSpace::LabCIE),
             "YUV" => Some(ColorSpace::YUV),
-            // "CMY" => Some(ColorSpace::CMY),
-            // "HunterLab" => Some(ColorSpace::HunterLab),
-            // "LCH" => Some(ColorSpace::LCH),
-            // "LUV" => Some(ColorSpace::LUV),
-            // "XYZ" => Some(ColorSpace::XYZ),
-            // "YXY" => Some(ColorSpace::YXY),
+            "CMY" => Some(ColorSpace::CMY),
+            "HunterLab" | "Hunter Lab" => Some(ColorSpace::HunterLab),
+            "LCH" | "LCh" => Some(ColorSpace::LCH),
+            "LUV" | "Luv" => Some(ColorSpace::LUV),
+            "XYZ" => Some(ColorSpace::XYZ),
+            "YXY" | "Yxy" | "xyY" => Some(ColorSpace::YXY),
+            "YCbCr601" => Some(ColorSpace::YCbCr601),
+            "YCbCr601Studio" => Some(ColorSpace::YCbCr601Studio),
+            "YCbCr709" => Some(ColorSpace::YCbCr709),
+            "YCbCr709Studio" => Some(ColorSpace::YCbCr709Studio),
+            "YCbCr2020" => Some(ColorSpace::YCbCr2020),
+            "YCbCr2020Studio" => Some(ColorSpace::YCbCr2020Studio),
+            "YIQ" => Some(ColorSpace::YIQ),
+            "OKLab" | "Oklab" => Some(ColorSpace::OKLab),
+            "OKLCh" | "Oklch" => Some(ColorSpace::OKLCh),
             _ => None,
         }//end matching str
     }//end from_str()
@@ -61,12 +133,21 @@ impl ColorSpace {
             "HSI",
             "LabCIE",
             "YUV",
-            // "CMY",
-            // "HunterLab",
-            // "LCH",
-            // "LUV",
-            // "XYZ",
-            // "YXY",
+            "CMY",
+            "HunterLab",
+            "LCH",
+            "LUV",
+            "XYZ",
+            "YXY",
+            "YCbCr601",
+            "YCbCr601Studio",
+            "YCbCr709",
+            "YCbCr709Studio",
+            "YCbCr2020",
+            "YCbCr2020Studio",
+            "YIQ",
+            "OKLab",
+            "OKLCh",
         ].iter().map(|s| s.to_string()).collect()
     }//end get_variants()
 
@@ -78,12 +159,210 @@ impl ColorSpace {
             ColorSpace::HSI => ["Hue".to_string(),"Saturation".to_string(),"Intensity".to_string()],
             ColorSpace::LabCIE => ["L".to_string(),"a".to_string(),"b".to_string()],
             ColorSpace::YUV => ["Y".to_string(),"U".to_string(),"V".to_string()],
-            // ColorSpace::CMY => ["Cyan".to_string(),"Magenta".to_string(),"Yellow".to_string()],
-            // ColorSpace::HunterLab => ["".to_string(),"".to_string(),"".to_string()],
-            // ColorSpace::LCH => ["Luminance".to_string(),"Chroma".to_string(),"Hue".to_string()],
-            // ColorSpace::LUV => ["Luminance".to_string(),"U".to_string(),"V".to_string()],
-            // ColorSpace::XYZ => ["X".to_string(),"Y".to_string(),"Z".to_string()],
-            // ColorSpace::YXY => ["Y".to_string(),"X".to_string(),"Y".to_string()],
+            ColorSpace::CMY => ["Cyan".to_string(),"Magenta".to_string(),"Yellow".to_string()],
+            ColorSpace::HunterLab => ["L".to_string(),"a".to_string(),"b".to_string()],
+            ColorSpace::LCH => ["Lightness".to_string(),"Chroma".to_string(),"Hue".to_string()],
+            ColorSpace::LUV => ["Lightness".to_string(),"u".to_string(),"v".to_string()],
+            ColorSpace::XYZ => ["X".to_string(),"Y".to_string(),"Z".to_string()],
+            ColorSpace::YXY => ["Luminance Y".to_string(),"x".to_string(),"y".to_string()],
+            ColorSpace::YCbCr601 | ColorSpace::YCbCr601Studio |
+            ColorSpace::YCbCr709 | ColorSpace::YCbCr709Studio |
+            ColorSpace::YCbCr2020 | ColorSpace::YCbCr2020Studio => ["Luma Y".to_string(),"Cb".to_string(),"Cr".to_string()],
+            ColorSpace::YIQ => ["Luma Y".to_string(),"In-phase I".to_string(),"Quadrature Q".to_string()],
+            ColorSpace::OKLab => ["Lightness".to_string(),"a".to_string(),"b".to_string()],
+            ColorSpace::OKLCh => ["Lightness".to_string(),"Chroma".to_string(),"Hue".to_string()],
         }//end matching self to channel names
     }//end channel_names()
-}//end impl for ColorSpace
\ No newline at end of file
+
+    /// Gets the unit symbol of each channel's native range, or an empty
+    /// str for channels which are just plain numbers.
+    pub fn channel_units(&self) -> [&'static str; 3] {
+        match self {
+            ColorSpace::HSBorHSV | ColorSpace::HSL | ColorSpace::HSI => ["°","%","%"],
+            ColorSpace::CMY => ["%","%","%"],
+            ColorSpace::LCH | ColorSpace::OKLCh => ["","","°"],
+            _ => ["","",""],
+        }//end matching self to channel units
+    }//end channel_units()
+
+    /// Gets the index of the hue channel, if this color space has one.
+    /// Hue is circular, so thresholds on it can wrap around through 0.
+    pub fn hue_channel(&self) -> Option<usize> {
+        match self {
+            ColorSpace::HSBorHSV | ColorSpace::HSL | ColorSpace::HSI => Some(0),
+            ColorSpace::LCH | ColorSpace::OKLCh => Some(2),
+            _ => None,
+        }//end matching self to hue channel
+    }//end hue_channel()
+
+    /// Gets the (min, max) of each channel in its native units, such as
+    /// degrees of hue or percent saturation, which our [0,255] scale
+    /// maps linearly onto. XYZ depends on the white point, as it's
+    /// scaled so the white point is the max.
+    pub fn native_ranges(&self, white: WhitePoint) -> [(f32,f32); 3] {
+        match self {
+            ColorSpace::RGB => [(0.,255.),(0.,255.),(0.,255.)],
+            ColorSpace::HSBorHSV | ColorSpace::HSL | ColorSpace::HSI => [(0.,360.),(0.,100.),(0.,100.)],
+            ColorSpace::LabCIE => [(0.,100.),(-128.,128.),(-128.,128.)],
+            ColorSpace::YUV => [(0.,255.),(-128.,127.),(-128.,127.)],
+            ColorSpace::CMY => [(0.,100.),(0.,100.),(0.,100.)],
+            ColorSpace::HunterLab => [(0.,100.),(-70.,110.),(-200.,60.)],
+            ColorSpace::LCH => [(0.,100.),(0.,134.),(0.,360.)],
+            ColorSpace::LUV => [(0.,100.),(-84.,176.),(-135.,108.)],
+            ColorSpace::XYZ => white.xyz().map(|c| (0., c as f32)),
+            ColorSpace::YXY => [(0.,1.),(0.,1.),(0.,1.)],
+            // video folks think in the 8-bit code values themselves
+            ColorSpace::YCbCr601 | ColorSpace::YCbCr601Studio |
+            ColorSpace::YCbCr709 | ColorSpace::YCbCr709Studio |
+            ColorSpace::YCbCr2020 | ColorSpace::YCbCr2020Studio => [(0.,255.),(0.,255.),(0.,255.)],
+            ColorSpace::YIQ => [(0.,1.),(-0.5959,0.5959),(-0.5227,0.5227)],
+            ColorSpace::OKLab => [(0.,1.),(-0.24,0.28),(-0.32,0.2)],
+            ColorSpace::OKLCh => [(0.,1.),(0.,0.33),(0.,360.)],
+        }//end matching self to native ranges
+    }//end native_ranges()
+
+    /// Converts a value of channel from our [0,255] scale to native units
+    pub fn to_native(&self, channel: usize, internal: f32, white: WhitePoint) -> f32 {
+        let (min, max) = self.native_ranges(white)[channel];
+        min + internal * (max - min) / 255.
+    }//end to_native()
+
+    /// Converts a value of channel from native units to our [0,255] scale
+    pub fn to_internal(&self, channel: usize, native: f32, white: WhitePoint) -> f32 {
+        let (min, max) = self.native_ranges(white)[channel];
+        (native - min) * 255. / (max - min)
+    }//end to_internal()
+}//end impl for ColorSpace
+
+/// This enum represents the reference white that Lab, LCh, Luv
+/// and XYZ are computed relative to. sRGB is defined under D65,
+/// so colors are chromatically adapted (Bradford) to any other white.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+pub enum WhitePoint {
+    /// Horizon light, the ICC profile connection space white
+    D50,
+    /// Mid-morning daylight
+    D55,
+    /// Noon daylight, which sRGB is defined under
+    #[default]
+    D65,
+    /// Incandescent tungsten light
+    A,
+    /// Cool white fluorescent light
+    F2,
+    /// Any other white given as XYZ tristimulus values
+    Custom { x: f64, y: f64, z: f64 },
+}//end enum WhitePoint
+
+impl WhitePoint {
+    /// Gets a standard white point from its name, or a custom
+    /// one from its tristimulus values such as `0.9642,1,0.8252`
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(str: &str) -> Option<WhitePoint> {
+        match str {
+            "D50" | "d50" => Some(WhitePoint::D50),
+            "D55" | "d55" => Some(WhitePoint::D55),
+            "D65" | "d65" => Some(WhitePoint::D65),
+            "A" | "a" => Some(WhitePoint::A),
+            "F2" | "f2" => Some(WhitePoint::F2),
+            custom => {
+                let parts: Vec<f64> = custom.split(',')
+                    .map(|s| s.trim().parse::<f64>())
+                    .collect::<Result<Vec<f64>,_>>().ok()?;
+                match parts[..] {
+                    [x,y,z] if x > 0. && y > 0. && z > 0. => Some(WhitePoint::Custom { x, y, z }),
+                    _ => None,
+                }//end matching number and sign of tristimulus values
+            },
+        }//end matching str
+    }//end from_str()
+
+    pub fn get_variants() -> Vec<String> {
+        [
+            "D50",
+            "D55",
+            "D65",
+            "A",
+            "F2",
+            "Custom",
+        ].iter().map(|s| s.to_string()).collect()
+    }//end get_variants()
+
+    /// Gets the entry in get_variants() for this white point
+    pub fn variant_name(&self) -> String {
+        match self {
+            WhitePoint::Custom { .. } => "Custom".to_string(),
+            standard => format!("{:?}", standard),
+        }//end matching whether self has a name of its own
+    }//end variant_name()
+
+    /// Gets the XYZ tristimulus values of this white, with Y = 1
+    /// for the standard illuminants (CIE 1931 2° observer)
+    pub fn xyz(&self) -> [f64; 3] {
+        match self {
+            WhitePoint::D50 => [0.96422, 1., 0.82521],
+            WhitePoint::D55 => [0.95682, 1., 0.92149],
+            WhitePoint::D65 => [0.95047, 1., 1.08883],
+            WhitePoint::A => [1.09850, 1., 0.35585],
+            WhitePoint::F2 => [0.99187, 1., 0.67395],
+            WhitePoint::Custom { x, y, z } => [*x, *y, *z],
+        }//end matching self to tristimulus values
+    }//end xyz()
+}//end impl for WhitePoint
+
+/// This enum represents a part of an image picked out to sample,
+/// with points in pixels of the image it was picked on.
+#[derive(Clone,Debug,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub enum SampleRegion {
+    /// A single pixel
+    Point(u32,u32),
+    /// Every pixel in the rectangle between two opposite corners
+    Rect((u32,u32),(u32,u32)),
+    /// Every pixel inside the polygon with these vertices
+    Lasso(Vec<(u32,u32)>),
+}//end enum SampleRegion
+
+/// This enum represents how far a threshold taken from samples
+/// reaches past the lowest and highest values sampled.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum SamplePadding {
+    /// Pads each channel by this percent of its full range
+    Percent(f32),
+    /// Pads each channel by this many standard deviations of its samples
+    StdDevs(f32),
+}//end enum SamplePadding
+
+/// This enum represents what thresholding produces.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub enum OutputMode {
+    /// The original image with out-of-threshold pixels painted over
+    Overlay,
+    /// A grayscale mask with passing pixels white and the rest black
+    Mask,
+    /// A grayscale mask with passing pixels black and the rest white
+    InvertedMask,
+    /// An rgba image with out-of-threshold pixels fully transparent
+    Transparent,
+}//end enum OutputMode
+
+impl OutputMode {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(str: &str) -> Option<OutputMode> {
+        match str {
+            "Overlay" | "overlay" => Some(OutputMode::Overlay),
+            "Mask" | "mask" => Some(OutputMode::Mask),
+            "Inverted Mask" | "inverted-mask" => Some(OutputMode::InvertedMask),
+            "Transparent" | "transparent" => Some(OutputMode::Transparent),
+            _ => None,
+        }//end matching str
+    }//end from_str()
+
+    pub fn get_variants() -> Vec<String> {
+        [
+            "Overlay",
+            "Mask",
+            "Inverted Mask",
+            "Transparent",
+        ].iter().map(|s| s.to_string()).collect()
+    }//end get_variants()
+}//end impl for OutputMode
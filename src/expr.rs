@@ -0,0 +1,417 @@
+use crate::enums::{ColorSpace, WhitePoint};
+
+/// A rule for which pixels pass the threshold, built from conditions
+/// on the channels of color spaces combined with and, or and not.
+/// Values are kept on our [0,255] scale, while the text form, such as
+/// `hue in 20..40 and (saturation > 40 or lab.l < 20)`,
+/// uses the native units of each color space.
+#[derive(Clone,Debug,PartialEq)]
+pub enum ThreshExpr {
+    /// The channel's value is from min up to max, inclusive.
+    /// On a circular channel, like hue, a min above max goes around
+    /// through 0, and on other channels it matches nothing.
+    InRange { space: ColorSpace, channel: usize, min: f32, max: f32, circular: bool },
+    /// The channel's value compared against value
+    Compare { space: ColorSpace, channel: usize, op: CompareOp, value: f32 },
+    Not(Box<ThreshExpr>),
+    And(Box<ThreshExpr>, Box<ThreshExpr>),
+    Or(Box<ThreshExpr>, Box<ThreshExpr>),
+}//end enum ThreshExpr
+
+/// The comparisons allowed between a channel and a value
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum CompareOp {
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+}//end enum CompareOp
+
+impl CompareOp {
+    /// Gets the symbol for this comparison in expression text
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            CompareOp::Less => "<",
+            CompareOp::LessEq => "<=",
+            CompareOp::Greater => ">",
+            CompareOp::GreaterEq => ">=",
+        }//end matching self to symbol
+    }//end symbol()
+}//end impl for CompareOp
+
+impl ThreshExpr {
+    /// Builds the expression for a (min, max, pass) interval on channel
+    /// of space. Stop lets through what pass would not on a circular
+    /// channel, and only values between max and min on other channels.
+    pub fn interval(space: ColorSpace, channel: usize, (min, max, pass): (f32,f32,bool)) -> ThreshExpr {
+        let circular = space.hue_channel() == Some(channel);
+        match (pass, circular) {
+            (true, _) => ThreshExpr::InRange { space, channel, min, max, circular },
+            (false, true) => ThreshExpr::Not(Box::new(ThreshExpr::InRange { space, channel, min, max, circular })),
+            (false, false) => ThreshExpr::InRange { space, channel, min: max, max: min, circular },
+        }//end matching whether interval passes or stops
+    }//end interval()
+
+    /// Returns true if a pixel passes this expression, where values
+    /// gives the pixel on our [0,255] scale in the color space asked for
+    pub fn matches(&self, values: &impl Fn(ColorSpace) -> [f32; 3]) -> bool {
+        match self {
+            ThreshExpr::InRange { space, channel, min, max, circular } => {
+                let value = values(*space)[*channel];
+                match (min <= max, circular) {
+                    (true, _) => value >= *min && value <= *max,
+                    (false, true) => value >= *min || value <= *max,
+                    (false, false) => false,
+                }//end matching whether range wraps around
+            },
+            ThreshExpr::Compare { space, channel, op, value } => {
+                let pixel_value = values(*space)[*channel];
+                match op {
+                    CompareOp::Less => pixel_value < *value,
+                    CompareOp::LessEq => pixel_value <= *value,
+                    CompareOp::Greater => pixel_value > *value,
+                    CompareOp::GreaterEq => pixel_value >= *value,
+                }//end matching comparison
+            },
+            ThreshExpr::Not(inner) => !inner.matches(values),
+            ThreshExpr::And(left, right) => left.matches(values) && right.matches(values),
+            ThreshExpr::Or(left, right) => left.matches(values) || right.matches(values),
+        }//end matching kind of expression
+    }//end matches()
+
+    /// Gets each color space this expression needs pixels converted
+    /// into, in the order they first come up
+    pub fn spaces(&self) -> Vec<ColorSpace> {
+        let mut spaces = Vec::new();
+        self.collect_spaces(&mut spaces);
+        spaces
+    }//end spaces()
+
+    /// Adds the color spaces this expression uses to spaces, skipping repeats
+    fn collect_spaces(&self, spaces: &mut Vec<ColorSpace>) {
+        match self {
+            ThreshExpr::InRange { space, .. } | ThreshExpr::Compare { space, .. } => {
+                if !spaces.contains(space) {spaces.push(*space);}
+            },
+            ThreshExpr::Not(inner) => inner.collect_spaces(spaces),
+            ThreshExpr::And(left, right) | ThreshExpr::Or(left, right) => {
+                left.collect_spaces(spaces);
+                right.collect_spaces(spaces);
+            },
+        }//end matching kind of expression
+    }//end collect_spaces()
+
+    /// Combines exprs so all of them need to pass, or None if there aren't any
+    pub fn all(exprs: impl IntoIterator<Item = ThreshExpr>) -> Option<ThreshExpr> {
+        exprs.into_iter().reduce(|left, right| ThreshExpr::And(Box::new(left), Box::new(right)))
+    }//end all()
+
+    /// Combines exprs so any of them needs to pass, or None if there aren't any
+    pub fn any(exprs: impl IntoIterator<Item = ThreshExpr>) -> Option<ThreshExpr> {
+        exprs.into_iter().reduce(|left, right| ThreshExpr::Or(Box::new(left), Box::new(right)))
+    }//end any()
+
+    /// Parses expression text, with channel names and values in the
+    /// native units of color_space. Channels can also be named d1, d2
+    /// and d3, and keywords are case insensitive, so
+    /// `d1 in 240..20 AND NOT (saturation < 10 or value <= 5.5)`
+    /// is fine for HSB. Channels of other color spaces go after the
+    /// name of their space, like `lab.l > 40` or `rgb.blue < 200`.
+    pub fn parse(text: &str, color_space: ColorSpace, white_point: WhitePoint) -> Result<ThreshExpr, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0, color_space, white_point };
+        let expr = parser.parse_or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(token) => Err(format!("Expected and, or, or the end of the expression, but got {}", token.describe())),
+        }//end matching whether we used up every token
+    }//end parse()
+
+    /// Formats this expression as text which parse() reads back,
+    /// with channel names and values in the native units of each space.
+    /// Channels of spaces other than color_space are named with their space.
+    pub fn to_text(&self, color_space: ColorSpace, white_point: WhitePoint) -> String {
+        self.to_text_within(0, color_space, white_point)
+    }//end to_text()
+
+    /// Formats this expression, wrapped in parentheses if it binds
+    /// looser than an operator of the precedence given
+    fn to_text_within(&self, outer_precedence: u8, color_space: ColorSpace, white_point: WhitePoint) -> String {
+        let native = |space: ColorSpace, channel: usize, value: f32| format_number(space.to_native(channel, value, white_point));
+        let ident = |space: ColorSpace, channel: usize| match space == color_space {
+            true => channel_ident(space, channel),
+            false => format!("{}.{}", space_idents(space)[0], channel_ident(space, channel)),
+        };
+        let (precedence, text) = match self {
+            ThreshExpr::InRange { space, channel, min, max, .. } => (3, format!(
+                "{} in {}..{}", ident(*space, *channel), native(*space, *channel, *min), native(*space, *channel, *max)
+            )),
+            ThreshExpr::Compare { space, channel, op, value } => (3, format!(
+                "{} {} {}", ident(*space, *channel), op.symbol(), native(*space, *channel, *value)
+            )),
+            ThreshExpr::Not(inner) => (3, format!("not {}", inner.to_text_within(3, color_space, white_point))),
+            ThreshExpr::And(left, right) => (2, format!(
+                "{} and {}", left.to_text_within(2, color_space, white_point), right.to_text_within(2, color_space, white_point)
+            )),
+            ThreshExpr::Or(left, right) => (1, format!(
+                "{} or {}", left.to_text_within(1, color_space, white_point), right.to_text_within(1, color_space, white_point)
+            )),
+        };
+        // not goes straight onto a single condition, but needs parentheses around anything else
+        let needs_parens = match (outer_precedence, self) {
+            (3, ThreshExpr::InRange { .. } | ThreshExpr::Compare { .. }) => false,
+            (3, _) => true,
+            _ => precedence < outer_precedence,
+        };
+        match needs_parens {
+            true => format!("({text})"),
+            false => text,
+        }//end matching whether we need parentheses
+    }//end to_text_within()
+}//end impl for ThreshExpr
+
+/// Formats a native value with at most 3 decimals,
+/// dropping trailing zeros so whole numbers stay whole
+fn format_number(value: f32) -> String {
+    let text = format!("{:.3}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        _ => text.to_string(),
+    }//end matching whether we rounded to negative zero
+}//end format_number()
+
+/// Lowercases name and drops anything that isn't a letter or digit,
+/// so "Brightness/Value" and "brightness_value" compare equal
+fn normalize_name(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}//end normalize_name()
+
+/// Gets the names a color space goes by in expressions, the first
+/// of which is used when formatting them
+pub fn space_idents(color_space: ColorSpace) -> &'static [&'static str] {
+    match color_space {
+        ColorSpace::RGB => &["rgb"],
+        ColorSpace::HSBorHSV => &["hsv", "hsb"],
+        ColorSpace::HSL => &["hsl"],
+        ColorSpace::HSI => &["hsi"],
+        ColorSpace::LabCIE => &["lab", "labcie"],
+        ColorSpace::YUV => &["yuv"],
+        ColorSpace::CMY => &["cmy"],
+        ColorSpace::HunterLab => &["hunterlab"],
+        ColorSpace::LCH => &["lch"],
+        ColorSpace::LUV => &["luv"],
+        ColorSpace::XYZ => &["xyz"],
+        ColorSpace::YXY => &["xyy", "yxy"],
+        ColorSpace::YCbCr601 => &["ycbcr601"],
+        ColorSpace::YCbCr601Studio => &["ycbcr601studio"],
+        ColorSpace::YCbCr709 => &["ycbcr709"],
+        ColorSpace::YCbCr709Studio => &["ycbcr709studio"],
+        ColorSpace::YCbCr2020 => &["ycbcr2020"],
+        ColorSpace::YCbCr2020Studio => &["ycbcr2020studio"],
+        ColorSpace::YIQ => &["yiq"],
+        ColorSpace::OKLab => &["oklab"],
+        ColorSpace::OKLCh => &["oklch"],
+    }//end matching color space to names
+}//end space_idents()
+
+/// Finds the color space that name refers to in expressions
+pub fn resolve_space(name: &str) -> Result<ColorSpace, String> {
+    let name = normalize_name(name);
+    ColorSpace::get_variants().iter()
+        .filter_map(|variant| ColorSpace::from_str(variant))
+        .find(|space| space_idents(*space).contains(&name.as_str()))
+        .ok_or_else(|| format!("There's no color space called \"{name}\""))
+}//end resolve_space()
+
+/// Finds the channel of color_space that name refers to. Names are
+/// checked against d1, d2 and d3, then the full channel names, then
+/// single words of the channel names, like "value" from "Brightness/Value".
+pub fn resolve_channel(color_space: ColorSpace, name: &str) -> Result<usize, String> {
+    let name = normalize_name(name);
+    if let Some(channel) = ["d1","d2","d3"].iter().position(|d| *d == name) {return Ok(channel);}
+    let channel_names = color_space.channel_names();
+    if let Some(channel) = channel_names.iter().position(|full| normalize_name(full) == name) {return Ok(channel);}
+    let word_matches: Vec<usize> = channel_names.iter().enumerate()
+        .filter(|(_, full)| full.split([' ', '/']).any(|word| normalize_name(word) == name))
+        .map(|(channel, _)| channel)
+        .collect();
+    match word_matches[..] {
+        [channel] => Ok(channel),
+        [] => Err(format!("{:?} doesn't have a channel called \"{name}\", try one of {}", color_space, channel_names.join(", "))),
+        _ => Err(format!("\"{name}\" could be more than one channel of {:?}, try d1, d2 or d3", color_space)),
+    }//end matching how many channels name could be
+}//end resolve_channel()
+
+/// Gets a short name for channel of color_space that resolve_channel()
+/// reads back, preferring the first word of the channel's name
+pub fn channel_ident(color_space: ColorSpace, channel: usize) -> String {
+    let full_name = &color_space.channel_names()[channel];
+    let first_word = normalize_name(full_name.split([' ', '/']).next().unwrap_or(""));
+    [first_word, normalize_name(full_name)].into_iter()
+        .find(|ident| !ident.is_empty() && resolve_channel(color_space, ident) == Ok(channel))
+        .unwrap_or_else(|| format!("d{}", channel + 1))
+}//end channel_ident()
+
+/// The pieces expression text is split into before parsing
+#[derive(Clone,Debug,PartialEq)]
+enum Token {
+    Word(String),
+    Number(f32),
+    Op(CompareOp),
+    Range,
+    Open,
+    Close,
+}//end enum Token
+
+impl Token {
+    /// Describes the token for error messages
+    fn describe(&self) -> String {
+        match self {
+            Token::Word(word) => format!("\"{word}\""),
+            Token::Number(number) => format!("the number {number}"),
+            Token::Op(op) => format!("\"{}\"", op.symbol()),
+            Token::Range => "\"..\"".to_string(),
+            Token::Open => "\"(\"".to_string(),
+            Token::Close => "\")\"".to_string(),
+        }//end matching kind of token
+    }//end describe()
+}//end impl for Token
+
+/// Splits expression text into tokens
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let starts_number = c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit() || *n == '.'));
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            '(' => {tokens.push(Token::Open); i += 1;},
+            ')' => {tokens.push(Token::Close); i += 1;},
+            '<' | '>' => {
+                let or_equal = chars.get(i + 1) == Some(&'=');
+                tokens.push(Token::Op(match (c, or_equal) {
+                    ('<', false) => CompareOp::Less,
+                    ('<', true) => CompareOp::LessEq,
+                    (_, false) => CompareOp::Greater,
+                    (_, true) => CompareOp::GreaterEq,
+                }));
+                i += if or_equal {2} else {1};
+            },
+            '.' if chars.get(i + 1) == Some(&'.') => {tokens.push(Token::Range); i += 2;},
+            _ if starts_number || c == '.' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || (chars[i] == '.' && chars.get(i + 1) != Some(&'.'))) {i += 1;}
+                let number: String = chars[start..i].iter().collect();
+                tokens.push(Token::Number(number.parse::<f32>().map_err(|_| format!("Invalid number \"{number}\""))?));
+            },
+            _ if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                // a dot followed by a letter separates a space from its channel
+                while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '-' | '/')
+                    || (chars[i] == '.' && chars.get(i + 1).is_some_and(|n| n.is_alphabetic()))) {i += 1;}
+                tokens.push(Token::Word(chars[start..i].iter().collect()));
+            },
+            _ => return Err(format!("Unexpected \"{c}\" in expression")),
+        }//end matching start of token
+    }//end looping over each character
+    Ok(tokens)
+}//end tokenize()
+
+/// Recursive descent parser over tokens, where not binds tightest,
+/// then and, then or
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    color_space: ColorSpace,
+    white_point: WhitePoint,
+}//end struct Parser
+
+impl Parser {
+    /// Gets the next token without using it up
+    fn peek(&self) -> Option<&Token> {self.tokens.get(self.pos)}
+
+    /// Uses up and returns the next token
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }//end next()
+
+    /// Returns true and uses up the next token if it's the keyword given
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {self.pos += 1; true},
+            _ => false,
+        }//end matching whether next token is keyword
+    }//end eat_keyword()
+
+    /// Parses a number in the native units of channel of space, giving it on our scale
+    fn parse_value(&mut self, space: ColorSpace, channel: usize) -> Result<f32, String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(space.to_internal(channel, number, self.white_point)),
+            Some(other) => Err(format!("Expected a number but got {}", other.describe())),
+            None => Err("Expected a number but the expression ended".to_string()),
+        }//end matching next token
+    }//end parse_value()
+
+    fn parse_or(&mut self) -> Result<ThreshExpr, String> {
+        let mut expr = self.parse_and()?;
+        while self.eat_keyword("or") {
+            expr = ThreshExpr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }//end combining each or
+        Ok(expr)
+    }//end parse_or()
+
+    fn parse_and(&mut self) -> Result<ThreshExpr, String> {
+        let mut expr = self.parse_unary()?;
+        while self.eat_keyword("and") {
+            expr = ThreshExpr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }//end combining each and
+        Ok(expr)
+    }//end parse_and()
+
+    fn parse_unary(&mut self) -> Result<ThreshExpr, String> {
+        if self.eat_keyword("not") {return Ok(ThreshExpr::Not(Box::new(self.parse_unary()?)));}
+        match self.next() {
+            Some(Token::Open) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    Some(other) => Err(format!("Expected \")\" but got {}", other.describe())),
+                    None => Err("Missing \")\" at the end of the expression".to_string()),
+                }//end matching whether parentheses are closed
+            },
+            Some(Token::Word(name)) => {
+                let (space, channel) = match name.split_once('.') {
+                    Some((space_name, channel_name)) => {
+                        let space = resolve_space(space_name)?;
+                        (space, resolve_channel(space, channel_name)?)
+                    },
+                    None => (self.color_space, resolve_channel(self.color_space, &name)?),
+                };
+                match self.next() {
+                    Some(Token::Op(op)) => Ok(ThreshExpr::Compare { space, channel, op, value: self.parse_value(space, channel)? }),
+                    Some(Token::Word(word)) if word.eq_ignore_ascii_case("in") => {
+                        let min = self.parse_value(space, channel)?;
+                        match self.next() {
+                            Some(Token::Range) => (),
+                            Some(other) => return Err(format!("Expected \"..\" between min and max but got {}", other.describe())),
+                            None => return Err("Expected \"..\" between min and max but the expression ended".to_string()),
+                        }//end matching range separator
+                        let max = self.parse_value(space, channel)?;
+                        let circular = space.hue_channel() == Some(channel);
+                        Ok(ThreshExpr::InRange { space, channel, min, max, circular })
+                    },
+                    Some(other) => Err(format!("Expected a comparison or \"in\" after {name} but got {}", other.describe())),
+                    None => Err(format!("Expected a comparison or \"in\" after {name} but the expression ended")),
+                }//end matching kind of condition
+            },
+            Some(other) => Err(format!("Expected a channel, \"not\" or \"(\" but got {}", other.describe())),
+            None => Err("Expected a channel, \"not\" or \"(\" but the expression ended".to_string()),
+        }//end matching start of condition
+    }//end parse_unary()
+}//end impl for Parser
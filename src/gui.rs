@@ -1,12 +1,16 @@
-use std::{cell::{BorrowMutError, RefCell}, rc::Rc};
+use std::{cell::{BorrowMutError, RefCell}, rc::Rc, time::Instant};
 
-use color_space_threshold_helper::{enums::{ColorSpace, InterfaceMessage}, process::ThreshParams};
-use fltk::{app::{self, App, Receiver}, button::CheckButton, enums::{Align, Color}, group::{Flex, FlexType}, menu::Choice, prelude::{MenuExt, ValuatorExt}, valuator::HorValueSlider};
+use color_space_threshold_helper::{enums::{ColorSpace, InterfaceMessage, OutputMode, SamplePadding, SampleRegion, WhitePoint}, expr::ThreshExpr, process::{convert_from_rgb_f32, convert_from_rgb_native, ImageToProc, ThreshParams, ThreshRule, DEFAULT_THRESH_COLOR}};
+use fltk::{app::{self, App, Receiver, Sender}, button::CheckButton, enums::{Align, CallbackTrigger, Color}, group::{Flex, FlexType}, menu::Choice, prelude::{DisplayExt, InputExt, MenuExt, ValuatorExt}, valuator::HorValueSlider};
 use fltk::button::Button;
 use fltk::dialog::{self, FileDialogOptions, FileDialogType};
-use fltk::enums::FrameType;
+use fltk::draw;
+use fltk::enums::{ColorDepth, Event, Font, FrameType};
 use fltk::frame::Frame;
+use fltk::misc::{Progress, Spinner};
 use fltk::image::SharedImage;
+use fltk::input::Input;
+use fltk::text::{TextBuffer, TextDisplay};
 use fltk::group::{Group, Tile};
 use fltk::prelude::{ButtonExt, GroupExt, ImageExt, WidgetBase, WidgetExt};
 use fltk::window::Window;
@@ -15,14 +19,112 @@ const GROUP_FRAME: FrameType = FrameType::GtkThinUpBox;
 const BUTTON_FRAME: FrameType = FrameType::GtkRoundUpFrame;
 const BUTTON_DOWN_FRAME: FrameType = FrameType::GtkRoundDownFrame;
 const PASS_ALIGN: Align = Align::Inside.union(Align::Left);
+/// Side length of the squares drawn behind transparent images
+const CHECKER_SIZE: i32 = 8;
+/// How long params need to stay unchanged before the live preview updates
+const PREVIEW_DEBOUNCE_SECS: f64 = 0.15;
+/// Width of the buttons which add and remove ranges of a channel
+const RANGE_BTN_WIDTH: i32 = 70;
+/// How many pixels the mouse can move while pressed and still count as a click
+const SAMPLE_CLICK_DIST: i32 = 3;
+/// Height of the pixel inspector under the image
+const INSPECTOR_HEIGHT: i32 = 130;
+/// How many bars each channel's histogram has
+const HISTOGRAM_BINS: usize = 128;
+/// Height of each channel's histogram
+const HISTOGRAM_HEIGHT: i32 = 40;
+
+/// Tracks param changes so the live preview only re-thresholds once they settle
+struct PreviewDebounce {
+    last_change: Instant,
+    pending: bool,
+}//end struct PreviewDebounce
+
+/// The mouse positions, in window coordinates, of a drag across
+/// the image which picks out pixels to sample
+struct SampleDrag {
+    points: Vec<(i32,i32)>,
+    /// Whether every point is kept for a lasso, rather than just
+    /// the first and last as corners of a rectangle
+    lasso: bool,
+}//end struct SampleDrag
+
+/// What a channel's histogram draws
+#[derive(Default)]
+struct Histogram {
+    /// How many pixels of the image land in each bin, from the
+    /// bottom of the channel's range to the top
+    counts: Vec<u32>,
+    /// Whether the channel is a hue, so its ranges can wrap around
+    circular: bool,
+}//end struct Histogram
+
+/// Handles to the widgets which set one channel's threshold
+#[derive(Clone)]
+struct ChannelWidgets {
+    label: Frame,
+    pass_ck: CheckButton,
+    /// Shows the image's values for this channel under the ranges that pass
+    histogram: Frame,
+    histogram_data: Rc<RefCell<Histogram>>,
+    min_slider: HorValueSlider,
+    max_slider: HorValueSlider,
+    /// Rows for intervals past the first, which sit right after max_slider
+    extra_rows: Rc<RefCell<Vec<IntervalRow>>>,
+    param_flex: Flex,
+}//end struct ChannelWidgets
+
+/// Handles to the widgets of one extra interval of a channel
+#[derive(Clone)]
+struct IntervalRow {
+    row: Flex,
+    pass_ck: CheckButton,
+    min_slider: HorValueSlider,
+    max_slider: HorValueSlider,
+}//end struct IntervalRow
+
+/// Handles to the widgets which hold the list of rules,
+/// each of which picks its own color space and channel
+#[derive(Clone)]
+struct RuleWidgets {
+    /// The row with the button to add rules, which rules go after
+    header_row: Flex,
+    rows: Rc<RefCell<Vec<RuleRow>>>,
+    param_flex: Flex,
+    white_point_choice: Choice,
+    custom_white_point: Rc<RefCell<WhitePoint>>,
+}//end struct RuleWidgets
+
+/// Handles to the widgets of one rule, which take up two rows
+#[derive(Clone)]
+struct RuleRow {
+    top_row: Flex,
+    slider_row: Flex,
+    space_choice: Choice,
+    channel_choice: Choice,
+    pass_ck: CheckButton,
+    min_slider: HorValueSlider,
+    max_slider: HorValueSlider,
+}//end struct RuleRow
 
 pub struct GUI {
     ux_app: App,
     // ux_main_window: Window,
     image_loaded: Rc<RefCell<Option<SharedImage>>>,
     image_frame: Frame,
-    // msg_sender: Sender<InterfaceMessage>,
+    msg_sender: Sender<InterfaceMessage>,
     msg_receiver: Receiver<InterfaceMessage>,
+    preview_debounce: Rc<RefCell<PreviewDebounce>>,
+    ux_d1_label: Frame,
+    ux_d2_label: Frame,
+    ux_d3_label: Frame,
+    ux_param_flex: Flex,
+    ux_expression_input: Input,
+    ux_extra_rows: [Rc<RefCell<Vec<IntervalRow>>>; 3],
+    ux_histograms: [Frame; 3],
+    ux_histogram_data: [Rc<RefCell<Histogram>>; 3],
+    ux_rules_row: Flex,
+    ux_rule_rows: Rc<RefCell<Vec<RuleRow>>>,
     ux_d1l_slider: HorValueSlider,
     ux_d1h_slider: HorValueSlider,
     ux_d1_pass_ck: CheckButton,
@@ -33,6 +135,15 @@ pub struct GUI {
     ux_d3h_slider: HorValueSlider,
     ux_d3_pass_ck: CheckButton,
     ux_color_space_choice: Choice,
+    ux_white_point_choice: Choice,
+    /// The last custom white point entered, used while "Custom" is chosen
+    custom_white_point: Rc<RefCell<WhitePoint>>,
+    ux_output_mode_choice: Choice,
+    ux_thresh_color_btn: Button,
+    ux_progress_bar: Progress,
+    ux_sample_padding_choice: Choice,
+    ux_sample_padding_spinner: Spinner,
+    ux_inspector_buffer: TextBuffer,
 }//end struct GUI
 
 impl GUI {
@@ -48,6 +159,10 @@ impl GUI {
         return self.msg_receiver.clone();
     }//end get_receiver()
 
+    pub fn get_sender(&self) -> Sender<InterfaceMessage> {
+        self.msg_sender
+    }//end get_sender()
+
     // Changes the displayed image to the one provided
     pub fn load_image(&mut self, image: SharedImage) -> Result<(),BorrowMutError> {
         let img_ref_clone = (&mut self.image_loaded).clone();
@@ -62,31 +177,205 @@ impl GUI {
         }//end matching 
     }//end load_image()
 
-    /// Tries to get threshold parameters from widgets
+    /// Tries to get threshold parameters from widgets,
+    /// converting the sliders from native units
     pub fn get_thresh_params(&self) -> Option<ThreshParams> {
-        let color_space = ColorSpace::from_str(&self.ux_color_space_choice.choice().unwrap_or_else(|| "".to_string()));
-        if color_space.is_none() {return None;}
-        let color_space = color_space.unwrap();
+        let color_space = color_space_from_choice(&self.ux_color_space_choice)?;
+        let white_point = white_point_from_choice(&self.ux_white_point_choice, &self.custom_white_point);
         
-        Some(ThreshParams {
-            color_space,
-            depth1_min: self.ux_d1l_slider.value() as u8,
-            depth1_max: self.ux_d1h_slider.value() as u8,
-            depth1_pass: self.ux_d1_pass_ck.is_checked(),
-            depth2_min: self.ux_d2l_slider.value() as u8,
-            depth2_max: self.ux_d2h_slider.value() as u8,
-            depth2_pass: self.ux_d2_pass_ck.is_checked(),
-            depth3_min: self.ux_d3l_slider.value() as u8,
-            depth3_max: self.ux_d3h_slider.value() as u8,
-            depth3_pass: self.ux_d3_pass_ck.is_checked(),
-        })
+        let intervals = self.channel_widgets().map(|channel| channel_intervals(&channel));
+        let expression = self.ux_expression_input.value();
+        let rules = self.ux_rule_rows.borrow().iter()
+            .filter_map(|row| rule_from_row(row, white_point))
+            .collect();
+        let params = ThreshParams {
+            expression: match expression.trim() {
+                "" => None,
+                _ => Some(expression),
+            },
+            rules,
+            ..ThreshParams::from_native_intervals(color_space, white_point, intervals)
+        };
+        // the expression input shows what's wrong with it
+        params.to_expr().ok()?;
+        Some(params)
     }//end get_thresh_params()
 
+    /// Fills the expression input with the expression the sliders
+    /// stand for, so it can be edited from there
+    pub fn expression_from_sliders(&mut self) {
+        self.ux_expression_input.set_value("");
+        if let Some(params) = self.get_thresh_params() {
+            self.ux_expression_input.set_value(&params.depths_expr().to_text(params.color_space, params.white_point));
+        }//end if we can get params from the sliders
+        check_expression(&mut self.ux_expression_input, &self.ux_color_space_choice, &self.ux_white_point_choice, &self.custom_white_point);
+    }//end expression_from_sliders()
+
+    /// Gets how far sampling should pad past the sampled values
+    fn sample_padding(&self) -> SamplePadding {
+        let amount = self.ux_sample_padding_spinner.value() as f32;
+        match self.ux_sample_padding_choice.value() {
+            1 => SamplePadding::StdDevs(amount),
+            _ => SamplePadding::Percent(amount),
+        }//end matching kind of padding picked
+    }//end sample_padding()
+
+    /// Sets each channel's sliders to the range of the pixels given in the
+    /// color space picked, padded as picked. Extra ranges and the expression
+    /// are cleared so the sliders take effect, while rules are kept.
+    pub fn apply_samples(&mut self, pixels: &[[u8; 3]]) {
+        let Some(color_space) = color_space_from_choice(&self.ux_color_space_choice) else {return;};
+        let white_point = white_point_from_choice(&self.ux_white_point_choice, &self.custom_white_point);
+        let Some(sampled) = ThreshParams::from_samples(color_space, white_point, pixels, self.sample_padding()) else {return;};
+        let rules = self.ux_rule_rows.borrow().iter()
+            .filter_map(|row| rule_from_row(row, white_point))
+            .collect();
+        self.set_thresh_params(&ThreshParams { rules, ..sampled });
+    }//end apply_samples()
+
+    /// Shows the pixel at pos of the original image, with the rgb given,
+    /// in the inspector, along with how it fares against the threshold
+    pub fn inspect_pixel(&mut self, pos: (u32,u32), rgb: [u8; 3]) {
+        let white_point = white_point_from_choice(&self.ux_white_point_choice, &self.custom_white_point);
+        let params = self.get_thresh_params();
+        self.ux_inspector_buffer.set_text(&pixel_info_text(pos, rgb, params.as_ref(), white_point));
+    }//end inspect_pixel()
+
+    /// Recounts each channel's histogram from img in the color space
+    /// and white point picked
+    pub fn update_histograms(&mut self, img: &ImageToProc) {
+        let color_space = color_space_from_choice(&self.ux_color_space_choice).unwrap_or(ColorSpace::RGB);
+        let white_point = white_point_from_choice(&self.ux_white_point_choice, &self.custom_white_point);
+        let counts = img.channel_histograms(color_space, white_point, HISTOGRAM_BINS);
+        let hue_idx = color_space.hue_channel();
+        for (i, (mut channel, counts)) in self.channel_widgets().into_iter().zip(counts).enumerate() {
+            *channel.histogram_data.borrow_mut() = Histogram { counts, circular: hue_idx == Some(i) };
+            channel.histogram.redraw();
+        }//end updating each channel's histogram
+    }//end update_histograms()
+
+    /// Updates the progress bar to show the percent and label given
+    pub fn set_progress(&mut self, percent: f64, label: &str) {
+        self.ux_progress_bar.set_value(percent);
+        self.ux_progress_bar.set_label(label);
+        self.ux_progress_bar.redraw();
+    }//end set_progress()
+
+    /// Asks for the live preview to be updated once params stop changing
+    pub fn request_preview(&self) {
+        request_preview(&self.msg_sender, &self.preview_debounce);
+    }//end request_preview()
+
+    /// Puts the text given on the clipboard
+    pub fn copy_to_clipboard(&self, text: &str) {
+        app::copy(text);
+    }//end copy_to_clipboard()
+
+    /// Pushes the threshold parameters given back into the widgets
+    pub fn set_thresh_params(&mut self, params: &ThreshParams) {
+        let choice_idx = ColorSpace::get_variants().iter()
+            .position(|v| ColorSpace::from_str(v) == Some(params.color_space));
+        if let Some(choice_idx) = choice_idx {
+            self.ux_color_space_choice.set_value(choice_idx as i32);
+        }//end if we found the color space in the choice
+        self.set_white_point(params.white_point);
+        show_native_channels(&mut self.channel_widgets(), params.color_space, params.white_point);
+        let depths = params.native_depths();
+        for (mut channel, (min, max, _)) in self.channel_widgets().into_iter().zip(depths) {
+            channel.min_slider.set_value(min as f64);
+            channel.max_slider.set_value(max as f64);
+        }//end setting each pair of sliders
+        self.ux_d1_pass_ck.set_checked(params.depth1_pass);
+        self.ux_d2_pass_ck.set_checked(params.depth2_pass);
+        self.ux_d3_pass_ck.set_checked(params.depth3_pass);
+        for (channel, intervals) in self.channel_widgets().iter_mut().zip(params.native_intervals()) {
+            for interval in intervals.into_iter().skip(1) {
+                add_interval_row(channel, interval, &self.msg_sender, &self.preview_debounce);
+            }//end adding a row for each extra interval
+            channel.histogram.redraw();
+        }//end adding extra intervals of each channel
+        self.ux_expression_input.set_value(params.expression.as_deref().unwrap_or(""));
+        check_expression(&mut self.ux_expression_input, &self.ux_color_space_choice, &self.ux_white_point_choice, &self.custom_white_point);
+        let rules = self.rule_widgets();
+        remove_all_rules(&rules);
+        for rule in params.rules.iter() {
+            add_rule_row(&rules, rule, &self.msg_sender, &self.preview_debounce);
+        }//end adding a row for each rule
+    }//end set_thresh_params()
+
+    /// Shows the white point given in the white point choice,
+    /// remembering its values if it's a custom one
+    fn set_white_point(&mut self, white_point: WhitePoint) {
+        if let WhitePoint::Custom { .. } = white_point {
+            *self.custom_white_point.borrow_mut() = white_point;
+        }//end if we need to remember custom values
+        let choice_idx = WhitePoint::get_variants().iter()
+            .position(|v| *v == white_point.variant_name());
+        if let Some(choice_idx) = choice_idx {
+            self.ux_white_point_choice.set_value(choice_idx as i32);
+        }//end if we found the white point in the choice
+    }//end set_white_point()
+
+    /// Gets whether the user wants to view an overlay or a mask
+    pub fn get_output_mode(&self) -> OutputMode {
+        OutputMode::from_str(&self.ux_output_mode_choice.choice().unwrap_or_default())
+            .unwrap_or(OutputMode::Overlay)
+    }//end get_output_mode()
+
+    /// Puts all the sliders and pass checkboxes back to their defaults,
+    /// removing any extra ranges, rules, and the expression
+    pub fn reset_params(&mut self) {
+        remove_all_rules(&self.rule_widgets());
+        self.ux_expression_input.set_value("");
+        check_expression(&mut self.ux_expression_input, &self.ux_color_space_choice, &self.ux_white_point_choice, &self.custom_white_point);
+        for pass_ck in [&self.ux_d1_pass_ck, &self.ux_d2_pass_ck, &self.ux_d3_pass_ck] {
+            pass_ck.set_checked(true);
+        }//end resetting each pass checkbox
+        self.set_white_point(WhitePoint::default());
+        let color_space = color_space_from_choice(&self.ux_color_space_choice).unwrap_or(ColorSpace::RGB);
+        show_native_channels(&mut self.channel_widgets(), color_space, WhitePoint::default());
+    }//end reset_params()
+
+    /// Gets handles to the widgets of each channel
+    fn channel_widgets(&self) -> [ChannelWidgets; 3] {
+        [
+            (&self.ux_d1_label, &self.ux_d1_pass_ck, &self.ux_d1l_slider, &self.ux_d1h_slider, 0),
+            (&self.ux_d2_label, &self.ux_d2_pass_ck, &self.ux_d2l_slider, &self.ux_d2h_slider, 1),
+            (&self.ux_d3_label, &self.ux_d3_pass_ck, &self.ux_d3l_slider, &self.ux_d3h_slider, 2),
+        ].map(|(label, pass_ck, min_slider, max_slider, i)| ChannelWidgets {
+            label: label.clone(),
+            pass_ck: pass_ck.clone(),
+            histogram: self.ux_histograms[i].clone(),
+            histogram_data: self.ux_histogram_data[i].clone(),
+            min_slider: min_slider.clone(),
+            max_slider: max_slider.clone(),
+            extra_rows: self.ux_extra_rows[i].clone(),
+            param_flex: self.ux_param_flex.clone(),
+        })
+    }//end channel_widgets()
+
+    /// Gets handles to the widgets of the rule list
+    fn rule_widgets(&self) -> RuleWidgets {
+        RuleWidgets {
+            header_row: self.ux_rules_row.clone(),
+            rows: self.ux_rule_rows.clone(),
+            param_flex: self.ux_param_flex.clone(),
+            white_point_choice: self.ux_white_point_choice.clone(),
+            custom_white_point: self.custom_white_point.clone(),
+        }//end struct construction
+    }//end rule_widgets()
+
+    /// Colors the threshold color button to show the color given
+    pub fn set_thresh_color(&mut self, color: (u8,u8,u8)) {
+        self.ux_thresh_color_btn.set_color(Color::rgb_color(color.0, color.1, color.2));
+        self.ux_thresh_color_btn.redraw();
+    }//end set_thresh_color()
+
     pub fn initialize() -> GUI {
         // set up app, main window, channel stuff
         let csth_app = App::default();
         let mut main_window = Window::default()
-            .with_size(700, 435)
+            .with_size(1300, 600)
             .with_label("Color Space Threshold Helper");
         main_window.end();
         main_window.make_resizable(true);
@@ -129,19 +418,84 @@ impl GUI {
         // define widgets for the image group
         let mut img_display_frame = Frame::default()
             .with_pos(ux_image_group.x() + 1, ux_image_group.y() + 1)
-            .with_size(ux_image_group.w() - 2, ux_image_group.h() - 2);
+            .with_size(ux_image_group.w() - 2, ux_image_group.h() - 2 - INSPECTOR_HEIGHT);
         img_display_frame.set_frame(FrameType::EngravedFrame);
         ux_image_group.add(&img_display_frame);
+        ux_image_group.resizable(&img_display_frame);
+
+        let mut inspector_buffer = TextBuffer::default();
+        inspector_buffer.set_text("Hover over the image to inspect its pixels");
+        let mut inspector = TextDisplay::default()
+            .with_pos(img_display_frame.x(), img_display_frame.y() + img_display_frame.h())
+            .with_size(img_display_frame.w(), INSPECTOR_HEIGHT);
+        inspector.set_buffer(inspector_buffer.clone());
+        inspector.set_text_font(Font::Courier);
+        inspector.set_text_size(11);
+        inspector.set_tooltip("Values of the pixel under the mouse in every color space, on our 0-255 scale and in native units");
+        ux_image_group.add(&inspector);
         let img_ref: Rc<RefCell<Option<SharedImage>>> = Rc::from(RefCell::from(None));
+        let sample_drag: Rc<RefCell<Option<SampleDrag>>> = Rc::from(RefCell::from(None));
         img_display_frame.draw({
             let img_ref_clone = (&img_ref).clone();
+            let drag_clone = sample_drag.clone();
             move |f| {
                 let mut img_ref = img_ref_clone.as_ref().borrow_mut();
                 if let Some(img) = img_ref.as_mut() {
                     img.scale(f.w(), f.h(), true, true);
+                    // show transparency over a checkerboard
+                    if matches!(img.depth(), ColorDepth::La8 | ColorDepth::Rgba8) {
+                        draw_checkerboard(f.x(), f.y(), img.w().min(f.w()), img.h().min(f.h()));
+                    }//end if image has an alpha channel
                     img.draw(f.x(), f.y(), f.w(), f.h());
                 }//end if we have an image to draw from
+                if let Some(drag) = drag_clone.borrow().as_ref() {
+                    draw_sample_drag(f, drag);
+                }//end if we're picking pixels to sample
         }});
+        img_display_frame.handle({
+            let img_ref_clone = img_ref.clone();
+            let drag_clone = sample_drag.clone();
+            let sender_clone = s;
+            move |f, event| match event {
+                Event::Push if app::event_button() == 1 && img_ref_clone.borrow().is_some() => {
+                    *drag_clone.borrow_mut() = Some(SampleDrag {
+                        points: vec![app::event_coords()],
+                        lasso: app::is_event_shift(),
+                    });
+                    true
+                },
+                Event::Drag => {
+                    let mut drag_ref = drag_clone.borrow_mut();
+                    let Some(drag) = drag_ref.as_mut() else {return false;};
+                    // rectangles only need the corner we started from
+                    if !drag.lasso {drag.points.truncate(1);}
+                    drag.points.push(app::event_coords());
+                    f.redraw();
+                    true
+                },
+                // taking enter is needed to get move events
+                Event::Enter => true,
+                Event::Move => {
+                    if let Some(img) = img_ref_clone.borrow().as_ref() {
+                        if let Some(pos) = frame_to_image(f, img, app::event_coords(), false) {
+                            sender_clone.send(InterfaceMessage::InspectPixel(pos, (img.data_w() as u32, img.data_h() as u32)));
+                        }//end if mouse is over the image
+                    }//end if we have an image to inspect
+                    true
+                },
+                Event::Released => {
+                    let Some(drag) = drag_clone.borrow_mut().take() else {return false;};
+                    f.redraw();
+                    if let Some(img) = img_ref_clone.borrow().as_ref() {
+                        if let Some(region) = sample_region(f, img, &drag) {
+                            sender_clone.send(InterfaceMessage::SamplePixels(region, (img.data_w() as u32, img.data_h() as u32)));
+                        }//end if drag picked out some of the image
+                    }//end if we have an image to sample
+                    true
+                },
+                _ => false,
+            }//end matching event
+        });
 
         // define widgets for the button group
         let mut get_files_btn = Button::default()
@@ -179,13 +533,31 @@ impl GUI {
 
         let mut color_space_choice = Choice::default();
         color_space_choice.add_choice(&ColorSpace::get_variants().join("|"));
+        color_space_choice.set_value(0);
         ux_button_flex.add(&color_space_choice);
 
+        let mut white_point_choice = Choice::default();
+        white_point_choice.add_choice(&WhitePoint::get_variants().join("|"));
+        let default_white_idx = WhitePoint::get_variants().iter()
+            .position(|v| *v == WhitePoint::default().variant_name())
+            .unwrap_or(0);
+        white_point_choice.set_value(default_white_idx as i32);
+        white_point_choice.set_tooltip("The reference white for Lab, LCh, Luv and XYZ");
+        ux_button_flex.add(&white_point_choice);
+        let custom_white_point = Rc::from(RefCell::from(WhitePoint::Custom { x: 0.95047, y: 1., z: 1.08883 }));
+
+        let mut output_mode_choice = Choice::default();
+        output_mode_choice.add_choice(&OutputMode::get_variants().join("|"));
+        output_mode_choice.set_value(0);
+        output_mode_choice.set_tooltip("Whether to view the threshold as an overlay or a mask");
+        ux_button_flex.add(&output_mode_choice);
+
         let mut thresh_color_btn = Button::default()
             .with_label("Threshold Color");
         thresh_color_btn.set_frame(BUTTON_FRAME);
         thresh_color_btn.set_down_frame(BUTTON_DOWN_FRAME);
         thresh_color_btn.clear_visible_focus();
+        thresh_color_btn.set_color(Color::rgb_color(DEFAULT_THRESH_COLOR.0, DEFAULT_THRESH_COLOR.1, DEFAULT_THRESH_COLOR.2));
         ux_button_flex.add(&thresh_color_btn);
         thresh_color_btn.set_callback({
             let sender_clone = s.clone();
@@ -215,11 +587,151 @@ impl GUI {
             }//end closure
         });
 
+        let mut save_img_btn = Button::default()
+            .with_label("Save Image");
+        save_img_btn.set_frame(BUTTON_FRAME);
+        save_img_btn.set_down_frame(BUTTON_DOWN_FRAME);
+        save_img_btn.clear_visible_focus();
+        ux_button_flex.add(&save_img_btn);
+        save_img_btn.set_callback({
+            let sender_clone = s;
+            move |_| {
+                let mut dialog = dialog::NativeFileChooser::new(FileDialogType::BrowseSaveFile);
+                dialog.set_option(FileDialogOptions::SaveAsConfirm.union(FileDialogOptions::UseFilterExt));
+                dialog.set_filter("PNG\t*.png\nTIFF\t*.{tif,tiff}\nJPEG\t*.{jpg,jpeg}\nWebP\t*.webp\nBMP\t*.bmp");
+                dialog.set_preset_file("thresholded.png");
+                dialog.set_title("Save Thresholded Image");
+                dialog.show();
+                // make sure dialog didn't have an error or anything
+                let dialog_error = dialog.error_message().unwrap_or_default().replace("No error", "");
+                if !dialog_error.is_empty() {println!("Encountered dialog error: {dialog_error}");}
+                else if dialog.filename().as_os_str().is_empty() {println!("No save location chosen");}
+                else {sender_clone.send(InterfaceMessage::SaveImage(dialog.filename()));}
+            }//end closure
+        });
+
+        let mut save_preset_btn = Button::default()
+            .with_label("Save Preset");
+        save_preset_btn.set_frame(BUTTON_FRAME);
+        save_preset_btn.set_down_frame(BUTTON_DOWN_FRAME);
+        save_preset_btn.clear_visible_focus();
+        ux_button_flex.add(&save_preset_btn);
+        save_preset_btn.set_callback({
+            let sender_clone = s;
+            move |_| {
+                let mut dialog = dialog::NativeFileChooser::new(FileDialogType::BrowseSaveFile);
+                dialog.set_option(FileDialogOptions::SaveAsConfirm.union(FileDialogOptions::UseFilterExt));
+                dialog.set_filter("Threshold Preset\t*.toml");
+                dialog.set_preset_file("preset.toml");
+                dialog.set_title("Save Threshold Preset");
+                dialog.show();
+                // make sure dialog didn't have an error or anything
+                let dialog_error = dialog.error_message().unwrap_or_default().replace("No error", "");
+                if !dialog_error.is_empty() {println!("Encountered dialog error: {dialog_error}");}
+                else if dialog.filename().as_os_str().is_empty() {println!("No save location chosen");}
+                else {sender_clone.send(InterfaceMessage::SavePreset(dialog.filename()));}
+            }//end closure
+        });
+
+        let mut load_preset_btn = Button::default()
+            .with_label("Load Preset");
+        load_preset_btn.set_frame(BUTTON_FRAME);
+        load_preset_btn.set_down_frame(BUTTON_DOWN_FRAME);
+        load_preset_btn.clear_visible_focus();
+        ux_button_flex.add(&load_preset_btn);
+        load_preset_btn.set_callback({
+            let sender_clone = s;
+            move |_| {
+                let mut dialog = dialog::NativeFileChooser::new(FileDialogType::BrowseFile);
+                dialog.set_option(FileDialogOptions::UseFilterExt);
+                dialog.set_filter("Threshold Preset\t*.toml");
+                dialog.set_title("Select Threshold Preset");
+                dialog.show();
+                // make sure dialog didn't have an error or anything
+                let dialog_error = dialog.error_message().unwrap_or_default().replace("No error", "");
+                if !dialog_error.is_empty() {println!("Encountered dialog error: {dialog_error}");}
+                else if dialog.filename().as_os_str().is_empty() {println!("No preset chosen");}
+                else {sender_clone.send(InterfaceMessage::LoadPreset(dialog.filename()));}
+            }//end closure
+        });
+
+        let mut imagej_btn = Button::default()
+            .with_label("ImageJ Macro");
+        imagej_btn.set_frame(BUTTON_FRAME);
+        imagej_btn.set_down_frame(BUTTON_DOWN_FRAME);
+        imagej_btn.clear_visible_focus();
+        imagej_btn.set_tooltip("Copy the threshold as ImageJ Color Threshold macro code");
+        ux_button_flex.add(&imagej_btn);
+        imagej_btn.set_callback({
+            let sender_clone = s;
+            move |_| {sender_clone.send(InterfaceMessage::ExportImageJ);}
+        });
+
+        let mut opencv_btn = Button::default()
+            .with_label("OpenCV Bounds");
+        opencv_btn.set_frame(BUTTON_FRAME);
+        opencv_btn.set_down_frame(BUTTON_DOWN_FRAME);
+        opencv_btn.clear_visible_focus();
+        opencv_btn.set_tooltip("Copy the threshold as OpenCV inRange bounds");
+        ux_button_flex.add(&opencv_btn);
+        opencv_btn.set_callback({
+            let sender_clone = s;
+            move |_| {sender_clone.send(InterfaceMessage::ExportOpenCV);}
+        });
+
+        let mut progress_bar = Progress::default();
+        progress_bar.set_minimum(0.);
+        progress_bar.set_maximum(100.);
+        progress_bar.set_value(0.);
+        progress_bar.set_frame(BUTTON_FRAME);
+        progress_bar.set_selection_color(Color::rgb_color(90,160,230));
+        ux_button_flex.add(&progress_bar);
+
         // define widgets for the param group
+        let mut sample_row = Flex::default();
+        sample_row.end();
+        sample_row.set_type(FlexType::Row);
+        ux_param_flex.add(&sample_row);
+
+        let mut sample_label = Frame::default()
+            .with_label("Sample Padding")
+            .with_align(Align::Inside.union(Align::Left));
+        sample_label.set_tooltip("Click the image to sample a pixel into the sliders, drag to sample a rectangle, or shift-drag to sample a lasso");
+        sample_row.add(&sample_label);
+
+        let mut sample_padding_choice = Choice::default();
+        sample_padding_choice.add_choice("% of Range|Std Devs");
+        sample_padding_choice.set_value(0);
+        sample_padding_choice.set_tooltip("Whether to pad past the sampled values by a percent of each channel's range or by standard deviations of the samples");
+        sample_row.add(&sample_padding_choice);
+        sample_row.fixed(&sample_padding_choice, RANGE_BTN_WIDTH + 30);
+
+        let mut sample_padding_spinner = Spinner::default();
+        sample_padding_spinner.set_range(0., 100.);
+        sample_padding_spinner.set_step(0.5);
+        sample_padding_spinner.set_value(0.);
+        sample_padding_spinner.set_tooltip("How far to pad past the sampled values");
+        sample_row.add(&sample_padding_spinner);
+        sample_row.fixed(&sample_padding_spinner, RANGE_BTN_WIDTH);
+
+        let mut d1_label_row = Flex::default();
+        d1_label_row.end();
+        d1_label_row.set_type(FlexType::Row);
+        ux_param_flex.add(&d1_label_row);
+
         let d1_label = Frame::default()
             .with_label("Depth 1 Min/Max Sliders")
             .with_align(Align::Bottom.union(Align::Inside));
-        ux_param_flex.add(&d1_label);
+        d1_label_row.add(&d1_label);
+
+        let mut d1_add_btn = Button::default()
+            .with_label("+ Range");
+        d1_add_btn.set_frame(BUTTON_FRAME);
+        d1_add_btn.set_down_frame(BUTTON_DOWN_FRAME);
+        d1_add_btn.clear_visible_focus();
+        d1_add_btn.set_tooltip("Add another range, which values can pass through instead");
+        d1_label_row.add(&d1_add_btn);
+        d1_label_row.fixed(&d1_add_btn, RANGE_BTN_WIDTH);
 
         let mut d1_pass_chk = CheckButton::default()
             .with_label("Depth 1 Pass")
@@ -227,6 +739,10 @@ impl GUI {
         d1_pass_chk.set(true);
         ux_param_flex.add(&d1_pass_chk);
 
+        let d1_histogram = Frame::default();
+        ux_param_flex.add(&d1_histogram);
+        ux_param_flex.fixed(&d1_histogram, HISTOGRAM_HEIGHT);
+
         let mut d1l_slider = HorValueSlider::default();
         d1l_slider.set_minimum(0.);
         d1l_slider.set_maximum(255.);
@@ -244,10 +760,24 @@ impl GUI {
         let spacer_frame_1 = Frame::default();
         ux_param_flex.add(&spacer_frame_1);
 
+        let mut d2_label_row = Flex::default();
+        d2_label_row.end();
+        d2_label_row.set_type(FlexType::Row);
+        ux_param_flex.add(&d2_label_row);
+
         let d2_label = Frame::default()
             .with_label("Depth 2 Min/Max Sliders")
             .with_align(Align::Bottom.union(Align::Inside));
-        ux_param_flex.add(&d2_label);
+        d2_label_row.add(&d2_label);
+
+        let mut d2_add_btn = Button::default()
+            .with_label("+ Range");
+        d2_add_btn.set_frame(BUTTON_FRAME);
+        d2_add_btn.set_down_frame(BUTTON_DOWN_FRAME);
+        d2_add_btn.clear_visible_focus();
+        d2_add_btn.set_tooltip("Add another range, which values can pass through instead");
+        d2_label_row.add(&d2_add_btn);
+        d2_label_row.fixed(&d2_add_btn, RANGE_BTN_WIDTH);
 
         let mut d2_pass_chk = CheckButton::default()
             .with_label("Depth 2 Pass")
@@ -255,6 +785,10 @@ impl GUI {
         d2_pass_chk.set(true);
         ux_param_flex.add(&d2_pass_chk);
 
+        let d2_histogram = Frame::default();
+        ux_param_flex.add(&d2_histogram);
+        ux_param_flex.fixed(&d2_histogram, HISTOGRAM_HEIGHT);
+
         let mut d2l_slider = HorValueSlider::default();
         d2l_slider.set_minimum(0.);
         d2l_slider.set_maximum(255.);
@@ -272,10 +806,24 @@ impl GUI {
         let spacer_frame_2 = Frame::default();
         ux_param_flex.add(&spacer_frame_2);
 
+        let mut d3_label_row = Flex::default();
+        d3_label_row.end();
+        d3_label_row.set_type(FlexType::Row);
+        ux_param_flex.add(&d3_label_row);
+
         let d3_label = Frame::default()
             .with_label("Depth 3 Min/Max Sliders")
             .with_align(Align::Bottom.union(Align::Inside));
-        ux_param_flex.add(&d3_label);
+        d3_label_row.add(&d3_label);
+
+        let mut d3_add_btn = Button::default()
+            .with_label("+ Range");
+        d3_add_btn.set_frame(BUTTON_FRAME);
+        d3_add_btn.set_down_frame(BUTTON_DOWN_FRAME);
+        d3_add_btn.clear_visible_focus();
+        d3_add_btn.set_tooltip("Add another range, which values can pass through instead");
+        d3_label_row.add(&d3_add_btn);
+        d3_label_row.fixed(&d3_add_btn, RANGE_BTN_WIDTH);
 
         let mut d3_pass_chk = CheckButton::default()
             .with_label("Depth 3 Pass")
@@ -283,6 +831,10 @@ impl GUI {
         d3_pass_chk.set(true);
         ux_param_flex.add(&d3_pass_chk);
 
+        let d3_histogram = Frame::default();
+        ux_param_flex.add(&d3_histogram);
+        ux_param_flex.fixed(&d3_histogram, HISTOGRAM_HEIGHT);
+
         let mut d3l_slider = HorValueSlider::default();
         d3l_slider.set_minimum(0.);
         d3l_slider.set_maximum(255.);
@@ -297,16 +849,262 @@ impl GUI {
         d3h_slider.set_value(255.);
         ux_param_flex.add(&d3h_slider);
 
+        let spacer_frame_3 = Frame::default();
+        ux_param_flex.add(&spacer_frame_3);
+
+        let mut expression_row = Flex::default();
+        expression_row.end();
+        expression_row.set_type(FlexType::Row);
+        ux_param_flex.add(&expression_row);
+
+        let expression_label = Frame::default()
+            .with_label("Expression")
+            .with_align(Align::Inside.union(Align::Left));
+        expression_row.add(&expression_label);
+        expression_row.fixed(&expression_label, RANGE_BTN_WIDTH);
+
+        let mut expression_input = Input::default();
+        expression_input.set_trigger(CallbackTrigger::Changed);
+        expression_row.add(&expression_input);
+
+        let mut expression_btn = Button::default()
+            .with_label("From Sliders");
+        expression_btn.set_frame(BUTTON_FRAME);
+        expression_btn.set_down_frame(BUTTON_DOWN_FRAME);
+        expression_btn.clear_visible_focus();
+        expression_btn.set_tooltip("Start the expression from what the sliders pass");
+        expression_row.add(&expression_btn);
+        expression_row.fixed(&expression_btn, RANGE_BTN_WIDTH + 30);
+        expression_btn.set_callback({
+            let sender_clone = s;
+            move |_| {sender_clone.send(InterfaceMessage::ExpressionFromSliders);}
+        });
+
+        let mut rules_row = Flex::default();
+        rules_row.end();
+        rules_row.set_type(FlexType::Row);
+        ux_param_flex.add(&rules_row);
+
+        let rules_label = Frame::default()
+            .with_label("Rules in Any Color Space")
+            .with_align(Align::Bottom.union(Align::Inside));
+        rules_row.add(&rules_label);
+
+        let mut rule_add_btn = Button::default()
+            .with_label("+ Rule");
+        rule_add_btn.set_frame(BUTTON_FRAME);
+        rule_add_btn.set_down_frame(BUTTON_DOWN_FRAME);
+        rule_add_btn.clear_visible_focus();
+        rule_add_btn.set_tooltip("Add a rule on a channel of any color space, which values need to pass as well");
+        rules_row.add(&rule_add_btn);
+        rules_row.fixed(&rule_add_btn, RANGE_BTN_WIDTH);
+
+        // set up live preview, so changing any param re-thresholds once changes settle
+        let preview_debounce = Rc::from(RefCell::from(PreviewDebounce {
+            last_change: Instant::now(),
+            pending: false,
+        }));
+        output_mode_choice.set_callback({
+            let sender_clone = s;
+            let debounce_clone = preview_debounce.clone();
+            move |_| request_preview(&sender_clone, &debounce_clone)
+        });
+        check_expression(&mut expression_input, &color_space_choice, &white_point_choice, &custom_white_point);
+        expression_input.set_callback({
+            let sender_clone = s;
+            let debounce_clone = preview_debounce.clone();
+            let space_choice_clone = color_space_choice.clone();
+            let white_choice_clone = white_point_choice.clone();
+            let custom_clone = custom_white_point.clone();
+            move |input| {
+                check_expression(input, &space_choice_clone, &white_choice_clone, &custom_clone);
+                request_preview(&sender_clone, &debounce_clone);
+            }//end closure
+        });
+        let extra_rows: [Rc<RefCell<Vec<IntervalRow>>>; 3] = Default::default();
+        let histograms = [d1_histogram, d2_histogram, d3_histogram];
+        let histogram_data: [Rc<RefCell<Histogram>>; 3] = Default::default();
+        let channel_widgets = [
+            (&d1_label, &d1_pass_chk, &d1l_slider, &d1h_slider, 0),
+            (&d2_label, &d2_pass_chk, &d2l_slider, &d2h_slider, 1),
+            (&d3_label, &d3_pass_chk, &d3l_slider, &d3h_slider, 2),
+        ].map(|(label, pass_ck, min_slider, max_slider, i)| ChannelWidgets {
+            label: label.clone(),
+            pass_ck: pass_ck.clone(),
+            histogram: histograms[i].clone(),
+            histogram_data: histogram_data[i].clone(),
+            min_slider: min_slider.clone(),
+            max_slider: max_slider.clone(),
+            extra_rows: extra_rows[i].clone(),
+            param_flex: ux_param_flex.clone(),
+        });
+        // set up live preview for each channel's sliders and pass checkbox,
+        // along with its histogram, which shows the same ranges
+        for channel in channel_widgets.iter() {
+            for mut slider in [channel.min_slider.clone(), channel.max_slider.clone()] {
+                slider.set_callback({
+                    let sender_clone = s;
+                    let debounce_clone = preview_debounce.clone();
+                    let mut histogram_clone = channel.histogram.clone();
+                    move |_| {
+                        histogram_clone.redraw();
+                        request_preview(&sender_clone, &debounce_clone);
+                    }//end closure
+                });
+            }//end setting live preview callback for each slider
+            channel.pass_ck.clone().set_callback({
+                let sender_clone = s;
+                let debounce_clone = preview_debounce.clone();
+                let mut histogram_clone = channel.histogram.clone();
+                move |_| {
+                    histogram_clone.redraw();
+                    request_preview(&sender_clone, &debounce_clone);
+                }//end closure
+            });
+            let mut histogram = channel.histogram.clone();
+            histogram.draw({
+                let channel_clone = channel.clone();
+                move |f| draw_histogram(f, &channel_clone)
+            });
+            histogram.handle({
+                let channel_clone = channel.clone();
+                // the slider whose handle is being dragged
+                let mut grabbed: Option<HorValueSlider> = None;
+                move |f, event| match event {
+                    Event::Push => {
+                        let x = app::event_x();
+                        let sliders = [channel_clone.min_slider.clone(), channel_clone.max_slider.clone()];
+                        grabbed = sliders.into_iter().min_by_key(|slider| (histogram_x(f, slider, slider.value()) - x).abs());
+                        if let Some(slider) = grabbed.as_mut() {move_histogram_handle(f, slider, x);}
+                        true
+                    },
+                    Event::Drag => {
+                        if let Some(slider) = grabbed.as_mut() {move_histogram_handle(f, slider, app::event_x());}
+                        true
+                    },
+                    Event::Released => {
+                        grabbed = None;
+                        true
+                    },
+                    _ => false,
+                }//end matching event
+            });
+        }//end setting up each channel
+        for (add_btn, channel) in [&mut d1_add_btn, &mut d2_add_btn, &mut d3_add_btn].into_iter().zip(channel_widgets.iter()) {
+            add_btn.set_callback({
+                let sender_clone = s;
+                let debounce_clone = preview_debounce.clone();
+                let channel_clone = channel.clone();
+                move |_| {
+                    // start empty, so adding a range doesn't change what passes
+                    let bottom = channel_clone.min_slider.minimum() as f32;
+                    add_interval_row(&channel_clone, (bottom, bottom, true), &sender_clone, &debounce_clone);
+                }//end closure
+            });
+        }//end setting callback for each add range button
+        show_native_channels(&mut channel_widgets.clone(), color_space_from_choice(&color_space_choice).unwrap_or(ColorSpace::RGB), WhitePoint::default());
+        let rule_rows: Rc<RefCell<Vec<RuleRow>>> = Default::default();
+        let rule_widgets = RuleWidgets {
+            header_row: rules_row.clone(),
+            rows: rule_rows.clone(),
+            param_flex: ux_param_flex.clone(),
+            white_point_choice: white_point_choice.clone(),
+            custom_white_point: custom_white_point.clone(),
+        };
+        rule_add_btn.set_callback({
+            let sender_clone = s;
+            let debounce_clone = preview_debounce.clone();
+            let rules_clone = rule_widgets.clone();
+            let space_choice_clone = color_space_choice.clone();
+            move |_| {
+                // start with the whole range, so adding a rule doesn't change what passes
+                let rule = ThreshRule {
+                    color_space: color_space_from_choice(&space_choice_clone).unwrap_or(ColorSpace::RGB),
+                    channel: 0,
+                    min: 0.,
+                    max: 255.,
+                    pass: true,
+                };
+                add_rule_row(&rules_clone, &rule, &sender_clone, &debounce_clone);
+            }//end closure
+        });
+        color_space_choice.set_callback({
+            let sender_clone = s;
+            let debounce_clone = preview_debounce.clone();
+            let mut channels_clone = channel_widgets.clone();
+            let white_choice_clone = white_point_choice.clone();
+            let custom_clone = custom_white_point.clone();
+            let mut expression_clone = expression_input.clone();
+            move |choice| {
+                if let Some(color_space) = color_space_from_choice(choice) {
+                    let white_point = white_point_from_choice(&white_choice_clone, &custom_clone);
+                    show_native_channels(&mut channels_clone, color_space, white_point);
+                }//end if we know the new color space
+                // channel names differ between spaces
+                check_expression(&mut expression_clone, choice, &white_choice_clone, &custom_clone);
+                sender_clone.send(InterfaceMessage::UpdateHistograms);
+                request_preview(&sender_clone, &debounce_clone);
+            }//end closure
+        });
+
+        white_point_choice.set_callback({
+            let sender_clone = s;
+            let debounce_clone = preview_debounce.clone();
+            let custom_clone = custom_white_point.clone();
+            let mut channels_clone = channel_widgets.clone();
+            let space_choice_clone = color_space_choice.clone();
+            let rules_clone = rule_widgets.clone();
+            move |choice| {
+                if choice.choice().as_deref() == Some("Custom") {
+                    let current = custom_clone.borrow().xyz().map(|c| c.to_string()).join(",");
+                    let input = dialog::input_default("Custom white point as X,Y,Z", &current);
+                    match input.as_deref().and_then(WhitePoint::from_str) {
+                        Some(custom @ WhitePoint::Custom { .. }) => *custom_clone.borrow_mut() = custom,
+                        _ => {
+                            if input.is_some() {dialog::alert_default("Expected three positive numbers such as 0.9642,1,0.8252");}
+                            choice.set_value(default_white_idx as i32);
+                        },
+                    }//end matching whether we got a valid custom white point
+                }//end if user wants to enter their own white point
+                // only xyz's native range depends on the white point
+                let white_point = white_point_from_choice(choice, &custom_clone);
+                if color_space_from_choice(&space_choice_clone) == Some(ColorSpace::XYZ) {
+                    show_native_channels(&mut channels_clone, ColorSpace::XYZ, white_point);
+                }//end if slider ranges need updating
+                for row in rules_clone.rows.borrow_mut().iter_mut() {
+                    if color_space_from_choice(&row.space_choice) == Some(ColorSpace::XYZ) {
+                        show_rule_channel(row, white_point);
+                    }//end if rule's slider ranges need updating
+                }//end updating each rule
+                sender_clone.send(InterfaceMessage::UpdateHistograms);
+                request_preview(&sender_clone, &debounce_clone);
+            }//end closure
+        });
+
         // clean up, package stuff together, show window
         main_window.show();
-        main_window.set_callback(move |_| {s.send(InterfaceMessage::Quit)});
+        main_window.set_callback({
+            let sender_clone = s;
+            move |_| {sender_clone.send(InterfaceMessage::Quit)}
+        });
         GUI {
             ux_app: csth_app,
             // ux_main_window: main_window,
             image_loaded: img_ref,
             image_frame: img_display_frame,
-            // msg_sender: s,
+            msg_sender: s,
             msg_receiver: r,
+            preview_debounce,
+            ux_d1_label: d1_label,
+            ux_d2_label: d2_label,
+            ux_d3_label: d3_label,
+            ux_param_flex,
+            ux_expression_input: expression_input,
+            ux_extra_rows: extra_rows,
+            ux_histograms: histograms,
+            ux_histogram_data: histogram_data,
+            ux_rules_row: rules_row,
+            ux_rule_rows: rule_rows,
             ux_d1l_slider: d1l_slider,
             ux_d1h_slider: d1h_slider,
             ux_d1_pass_ck: d1_pass_chk,
@@ -317,6 +1115,599 @@ impl GUI {
             ux_d3h_slider: d3h_slider,
             ux_d3_pass_ck: d3_pass_chk,
             ux_color_space_choice: color_space_choice,
+            ux_white_point_choice: white_point_choice,
+            custom_white_point,
+            ux_output_mode_choice: output_mode_choice,
+            ux_thresh_color_btn: thresh_color_btn,
+            ux_progress_bar: progress_bar,
+            ux_sample_padding_choice: sample_padding_choice,
+            ux_sample_padding_spinner: sample_padding_spinner,
+            ux_inspector_buffer: inspector_buffer,
         }//end struct construction
     }//end initialize()
-}//end impl for GUI
\ No newline at end of file
+}//end impl for GUI
+
+/// Gets the color space currently picked in choice
+fn color_space_from_choice(choice: &Choice) -> Option<ColorSpace> {
+    ColorSpace::from_str(&choice.choice()?)
+}//end color_space_from_choice()
+
+/// Gets the white point currently picked in choice,
+/// using custom when "Custom" is picked
+fn white_point_from_choice(choice: &Choice, custom: &Rc<RefCell<WhitePoint>>) -> WhitePoint {
+    match choice.choice() {
+        Some(name) if name == "Custom" => *custom.borrow(),
+        Some(name) => WhitePoint::from_str(&name).unwrap_or_default(),
+        None => WhitePoint::default(),
+    }//end matching name of white point
+}//end white_point_from_choice()
+
+/// Labels each channel's widgets with its name and units in color_space,
+/// and sets its (min, max) sliders to span the native range of that
+/// channel, with the min slider at the bottom of the range and the max
+/// slider at the top. Narrow ranges, like OKLab's, get finer steps so
+/// they aren't stuck with only a few values. Any extra ranges are removed.
+fn show_native_channels(channels: &mut [ChannelWidgets; 3], color_space: ColorSpace, white_point: WhitePoint) {
+    let names = color_space.channel_names();
+    let units = color_space.channel_units();
+    let ranges = color_space.native_ranges(white_point);
+    for (i, channel) in channels.iter_mut().enumerate() {
+        // extra ranges don't mean anything in another space
+        let extra_rows: Vec<IntervalRow> = channel.extra_rows.borrow_mut().drain(..).collect();
+        for row in extra_rows {remove_interval_row(channel, row);}
+        let (min, max) = ranges[i];
+        let name = match units[i] {
+            "" => names[i].clone(),
+            unit => format!("{} ({unit})", names[i]),
+        };
+        channel.label.set_label(&format!("{name} Min/Max"));
+        channel.label.redraw();
+        channel.pass_ck.set_label(&format!("{} Pass", names[i]));
+        channel.pass_ck.set_tooltip(pass_tooltip(color_space, i));
+        channel.pass_ck.redraw();
+        let precision = slider_precision(min, max);
+        for (slider, bound) in [(&mut channel.min_slider, "Lowest"), (&mut channel.max_slider, "Highest")] {
+            slider.set_range(min as f64, max as f64);
+            slider.set_precision(precision);
+            slider.set_tooltip(&format!("{bound} {name} to threshold"));
+        }//end setting range of each slider
+        channel.min_slider.set_value(min as f64);
+        channel.max_slider.set_value(max as f64);
+        channel.min_slider.redraw();
+        channel.max_slider.redraw();
+        channel.histogram.redraw();
+    }//end setting up each channel
+}//end show_native_channels()
+
+/// Gets the tooltip for the pass checkbox of channel in color_space
+fn pass_tooltip(color_space: ColorSpace, channel: usize) -> &'static str {
+    match color_space.hue_channel() == Some(channel) {
+        true => "Pass or stop hues from min around to max, which wraps through 0 when min is above max",
+        false => "Pass values from min to max, or stop to only let through values from max to min",
+    }//end matching whether channel is a hue
+}//end pass_tooltip()
+
+/// Gets how many decimals sliders from min to max should show,
+/// so narrow ranges aren't stuck with only a few values
+fn slider_precision(min: f32, max: f32) -> i32 {
+    match max - min > 10. {
+        true => 1,
+        false => 3,
+    }//end matching whether range is narrow
+}//end slider_precision()
+
+/// Colors the text of the expression input red and puts the reason
+/// in its tooltip if it can't be parsed for the space and white point
+/// picked. An empty expression is fine, as the sliders are used instead.
+fn check_expression(input: &mut Input, space_choice: &Choice, white_choice: &Choice, custom_white: &Rc<RefCell<WhitePoint>>) {
+    let text = input.value();
+    let error = match (text.trim(), color_space_from_choice(space_choice)) {
+        ("", _) | (_, None) => None,
+        (_, Some(color_space)) => ThreshExpr::parse(&text, color_space, white_point_from_choice(white_choice, custom_white)).err(),
+    };
+    match error {
+        Some(error) => {
+            input.set_text_color(Color::Red);
+            input.set_tooltip(&error);
+        },
+        None => {
+            input.set_text_color(Color::Foreground);
+            input.set_tooltip("Combine channels in native units with and, or and not, such as \"hue in 20..40 and (saturation > 40 or brightness < 20)\". Leave empty to use the sliders.");
+        },
+    }//end matching whether the expression is valid
+    input.redraw();
+}//end check_expression()
+
+/// Adds a row of widgets for another (min, max, pass) interval of
+/// channel, in native units, right after the channel's other intervals.
+/// The row's sliders take the range and steps of the channel's sliders.
+fn add_interval_row(channel: &ChannelWidgets, interval: (f32,f32,bool), sender: &Sender<InterfaceMessage>, debounce: &Rc<RefCell<PreviewDebounce>>) {
+    let mut row = Flex::default();
+    row.end();
+    row.set_type(FlexType::Row);
+
+    let mut pass_ck = CheckButton::default()
+        .with_label("Or Pass")
+        .with_align(PASS_ALIGN);
+    pass_ck.set_checked(interval.2);
+    pass_ck.set_tooltip(&channel.pass_ck.tooltip().unwrap_or_default());
+    row.add(&pass_ck);
+    row.fixed(&pass_ck, RANGE_BTN_WIDTH);
+
+    let mut sliders = [channel.min_slider.clone(), channel.max_slider.clone()].map(|channel_slider| {
+        let mut slider = HorValueSlider::default();
+        slider.set_range(channel_slider.minimum(), channel_slider.maximum());
+        slider.set_step(channel_slider.step(), 1);
+        slider.set_tooltip(&channel_slider.tooltip().unwrap_or_default());
+        row.add(&slider);
+        slider
+    });
+    sliders[0].set_value(interval.0 as f64);
+    sliders[1].set_value(interval.1 as f64);
+
+    let mut remove_btn = Button::default()
+        .with_label("- Range");
+    remove_btn.set_frame(BUTTON_FRAME);
+    remove_btn.set_down_frame(BUTTON_DOWN_FRAME);
+    remove_btn.clear_visible_focus();
+    remove_btn.set_tooltip("Remove this range");
+    row.add(&remove_btn);
+    row.fixed(&remove_btn, RANGE_BTN_WIDTH);
+
+    let [min_slider, max_slider] = sliders;
+    let interval_row = IntervalRow { row: row.clone(), pass_ck, min_slider, max_slider };
+    for mut slider in [interval_row.min_slider.clone(), interval_row.max_slider.clone()] {
+        slider.set_callback({
+            let sender_clone = *sender;
+            let debounce_clone = debounce.clone();
+            let mut histogram_clone = channel.histogram.clone();
+            move |_| {
+                histogram_clone.redraw();
+                request_preview(&sender_clone, &debounce_clone);
+            }//end closure
+        });
+    }//end setting live preview callback for each slider
+    interval_row.pass_ck.clone().set_callback({
+        let sender_clone = *sender;
+        let debounce_clone = debounce.clone();
+        let mut histogram_clone = channel.histogram.clone();
+        move |_| {
+            histogram_clone.redraw();
+            request_preview(&sender_clone, &debounce_clone);
+        }//end closure
+    });
+    remove_btn.set_callback({
+        let sender_clone = *sender;
+        let debounce_clone = debounce.clone();
+        let channel_clone = channel.clone();
+        move |_| {
+            let mut extra_rows = channel_clone.extra_rows.borrow_mut();
+            let Some(idx) = extra_rows.iter().position(|r| r.row.as_widget_ptr() == row.as_widget_ptr()) else {return;};
+            let removed = extra_rows.remove(idx);
+            drop(extra_rows);
+            remove_interval_row(&channel_clone, removed);
+            request_preview(&sender_clone, &debounce_clone);
+        }//end closure
+    });
+
+    // go after the last row of this channel
+    let mut param_flex = channel.param_flex.clone();
+    let last_idx = match channel.extra_rows.borrow().last() {
+        Some(last) => param_flex.find(&last.row),
+        None => param_flex.find(&channel.max_slider),
+    };
+    param_flex.insert(&interval_row.row, last_idx + 1);
+    param_flex.layout();
+    param_flex.redraw();
+    channel.extra_rows.borrow_mut().push(interval_row);
+    channel.histogram.clone().redraw();
+    request_preview(sender, debounce);
+}//end add_interval_row()
+
+/// Gets the (min, max, pass) of each interval of channel, in native units
+fn channel_intervals(channel: &ChannelWidgets) -> Vec<(f32,f32,bool)> {
+    let mut intervals = vec![(channel.min_slider.value() as f32, channel.max_slider.value() as f32, channel.pass_ck.is_checked())];
+    intervals.extend(channel.extra_rows.borrow().iter()
+        .map(|row| (row.min_slider.value() as f32, row.max_slider.value() as f32, row.pass_ck.is_checked())));
+    intervals
+}//end channel_intervals()
+
+/// Draws the histogram of channel over frame, with the values its
+/// intervals let through shaded, and a handle for each of its sliders.
+/// The histogram spans the same native range as the sliders.
+fn draw_histogram(frame: &Frame, channel: &ChannelWidgets) {
+    let (x, y, w, h) = (frame.x(), frame.y(), frame.w(), frame.h());
+    if w <= 0 || h <= 0 {return;}
+    let histogram = channel.histogram_data.borrow();
+    let intervals = channel_intervals(channel);
+    let (bottom, top) = (channel.min_slider.minimum(), channel.min_slider.maximum());
+    draw::push_clip(x, y, w, h);
+    draw::draw_rect_fill(x, y, w, h, Color::Background2);
+    let tallest = histogram.counts.iter().copied().max().unwrap_or(0);
+    for col in 0..w {
+        let value = (bottom + (top - bottom) * (col as f64 + 0.5) / w as f64) as f32;
+        let passes = intervals.iter()
+            .any(|(min, max, pass)| ThreshParams::depth_passes(value, *min, *max, *pass, histogram.circular));
+        if passes {
+            draw::set_draw_color(Color::rgb_color(190,220,250));
+            draw::draw_line(x + col, y, x + col, y + h - 1);
+        }//end if column's value passes
+        if tallest > 0 {
+            let count = histogram.counts[col as usize * histogram.counts.len() / w as usize];
+            // square root scaling keeps rare values from vanishing next to common ones
+            let bar = ((count as f64 / tallest as f64).sqrt() * (h - 1) as f64).round() as i32;
+            if bar > 0 {
+                draw::set_draw_color(Color::rgb_color(90,90,90));
+                draw::draw_line(x + col, y + h - 1, x + col, y + h - bar);
+            }//end if there's a bar to draw
+        }//end if there are counts to draw
+    }//end drawing each column
+    for slider in [&channel.min_slider, &channel.max_slider] {
+        let handle_x = histogram_x(frame, slider, slider.value());
+        draw::set_draw_color(Color::Red);
+        draw::draw_line(handle_x, y, handle_x, y + h - 1);
+        draw::draw_polygon(handle_x - 4, y, handle_x + 4, y, handle_x, y + 6);
+    }//end drawing the handle of each slider
+    draw::pop_clip();
+}//end draw_histogram()
+
+/// Gets the x in window coordinates where value falls on the histogram
+/// in frame, which spans the range of slider
+fn histogram_x(frame: &Frame, slider: &HorValueSlider, value: f64) -> i32 {
+    let (bottom, top) = (slider.minimum(), slider.maximum());
+    let fraction = match top > bottom {
+        true => (value - bottom) / (top - bottom),
+        false => 0.,
+    };
+    frame.x() + (fraction * (frame.w() - 1) as f64).round() as i32
+}//end histogram_x()
+
+/// Moves slider to the value at x in window coordinates on the histogram
+/// in frame, letting the slider's callback update the preview
+fn move_histogram_handle(frame: &Frame, slider: &mut HorValueSlider, x: i32) {
+    let fraction = ((x - frame.x()) as f64 / (frame.w() - 1).max(1) as f64).clamp(0., 1.);
+    let value = slider.minimum() + fraction * (slider.maximum() - slider.minimum());
+    slider.set_value(slider.clamp(slider.round(value)));
+    slider.redraw();
+    slider.do_callback();
+}//end move_histogram_handle()
+
+/// Takes the widgets of row out of the param flex and deletes them.
+/// The row should already be out of the channel's extra_rows.
+fn remove_interval_row(channel: &ChannelWidgets, row: IntervalRow) {
+    let mut param_flex = channel.param_flex.clone();
+    param_flex.remove(&row.row);
+    delete_widget_later(row.row);
+    param_flex.layout();
+    param_flex.redraw();
+    channel.histogram.clone().redraw();
+}//end remove_interval_row()
+
+/// Deletes widget once fltk is done handling the current event.
+/// app::delete_widget() deletes right away, which would free the
+/// button whose callback is still running when a row removes itself.
+fn delete_widget_later<W: WidgetBase + 'static>(widget: W) {
+    let mut widget = Some(widget);
+    app::add_timeout3(0., move |_| {
+        if let Some(widget) = widget.take() {app::delete_widget(widget);}
+    });
+}//end delete_widget_later()
+
+/// Gets the rule that row stands for, converting its sliders from native units
+fn rule_from_row(row: &RuleRow, white_point: WhitePoint) -> Option<ThreshRule> {
+    let color_space = color_space_from_choice(&row.space_choice)?;
+    let channel = usize::try_from(row.channel_choice.value()).ok()?;
+    let interval = (row.min_slider.value() as f32, row.max_slider.value() as f32, row.pass_ck.is_checked());
+    Some(ThreshRule::from_native(color_space, channel, white_point, interval))
+}//end rule_from_row()
+
+/// Fills choice with the names of each channel of color_space,
+/// picking the first one
+fn show_channel_choices(choice: &mut Choice, color_space: ColorSpace) {
+    choice.clear();
+    for name in color_space.channel_names() {
+        // a slash would start a submenu
+        choice.add_choice(&name.replace('/', "\\/"));
+    }//end adding each channel name
+    choice.set_value(0);
+    choice.redraw();
+}//end show_channel_choices()
+
+/// Sets the sliders of row to span the native range of the channel
+/// picked, with the min slider at the bottom and the max slider at the top
+fn show_rule_channel(row: &mut RuleRow, white_point: WhitePoint) {
+    let Some(color_space) = color_space_from_choice(&row.space_choice) else {return;};
+    let Ok(channel @ 0..=2) = usize::try_from(row.channel_choice.value()) else {return;};
+    let (min, max) = color_space.native_ranges(white_point)[channel];
+    let name = &color_space.channel_names()[channel];
+    for (slider, bound) in [(&mut row.min_slider, "Lowest"), (&mut row.max_slider, "Highest")] {
+        slider.set_range(min as f64, max as f64);
+        slider.set_precision(slider_precision(min, max));
+        slider.set_tooltip(&format!("{bound} {name} to threshold"));
+    }//end setting range of each slider
+    row.min_slider.set_value(min as f64);
+    row.max_slider.set_value(max as f64);
+    row.min_slider.redraw();
+    row.max_slider.redraw();
+    row.pass_ck.set_tooltip(pass_tooltip(color_space, channel));
+}//end show_rule_channel()
+
+/// Adds the two rows of widgets for rule after the other rules, with
+/// a color space and channel choice, a pass checkbox, and sliders in
+/// the native units of the channel.
+fn add_rule_row(rules: &RuleWidgets, rule: &ThreshRule, sender: &Sender<InterfaceMessage>, debounce: &Rc<RefCell<PreviewDebounce>>) {
+    let white_point = white_point_from_choice(&rules.white_point_choice, &rules.custom_white_point);
+    let mut top_row = Flex::default();
+    top_row.end();
+    top_row.set_type(FlexType::Row);
+
+    let mut space_choice = Choice::default();
+    space_choice.add_choice(&ColorSpace::get_variants().join("|"));
+    let choice_idx = ColorSpace::get_variants().iter()
+        .position(|v| ColorSpace::from_str(v) == Some(rule.color_space));
+    space_choice.set_value(choice_idx.unwrap_or(0) as i32);
+    space_choice.set_tooltip("Color space this rule converts pixels into");
+    top_row.add(&space_choice);
+
+    let mut channel_choice = Choice::default();
+    show_channel_choices(&mut channel_choice, rule.color_space);
+    channel_choice.set_value(rule.channel as i32);
+    channel_choice.set_tooltip("Channel this rule thresholds");
+    top_row.add(&channel_choice);
+
+    let pass_ck = CheckButton::default()
+        .with_label("Pass")
+        .with_align(PASS_ALIGN);
+    pass_ck.set_checked(rule.pass);
+    top_row.add(&pass_ck);
+    top_row.fixed(&pass_ck, RANGE_BTN_WIDTH);
+
+    let mut remove_btn = Button::default()
+        .with_label("- Rule");
+    remove_btn.set_frame(BUTTON_FRAME);
+    remove_btn.set_down_frame(BUTTON_DOWN_FRAME);
+    remove_btn.clear_visible_focus();
+    remove_btn.set_tooltip("Remove this rule");
+    top_row.add(&remove_btn);
+    top_row.fixed(&remove_btn, RANGE_BTN_WIDTH);
+
+    let mut slider_row = Flex::default();
+    slider_row.end();
+    slider_row.set_type(FlexType::Row);
+    let [min_slider, max_slider] = [(); 2].map(|_| {
+        let slider = HorValueSlider::default();
+        slider_row.add(&slider);
+        slider
+    });
+
+    let mut rule_row = RuleRow { top_row: top_row.clone(), slider_row, space_choice, channel_choice, pass_ck, min_slider, max_slider };
+    show_rule_channel(&mut rule_row, white_point);
+    let (min, max, _) = rule.native_interval(white_point);
+    rule_row.min_slider.set_value(min as f64);
+    rule_row.max_slider.set_value(max as f64);
+
+    for mut slider in [rule_row.min_slider.clone(), rule_row.max_slider.clone()] {
+        slider.set_callback({
+            let sender_clone = *sender;
+            let debounce_clone = debounce.clone();
+            move |_| request_preview(&sender_clone, &debounce_clone)
+        });
+    }//end setting live preview callback for each slider
+    rule_row.pass_ck.clone().set_callback({
+        let sender_clone = *sender;
+        let debounce_clone = debounce.clone();
+        move |_| request_preview(&sender_clone, &debounce_clone)
+    });
+    rule_row.space_choice.clone().set_callback({
+        let sender_clone = *sender;
+        let debounce_clone = debounce.clone();
+        let rules_clone = rules.clone();
+        let mut row_clone = rule_row.clone();
+        move |choice| {
+            if let Some(color_space) = color_space_from_choice(choice) {
+                show_channel_choices(&mut row_clone.channel_choice, color_space);
+                show_rule_channel(&mut row_clone, white_point_from_choice(&rules_clone.white_point_choice, &rules_clone.custom_white_point));
+            }//end if we know the new color space
+            request_preview(&sender_clone, &debounce_clone);
+        }//end closure
+    });
+    rule_row.channel_choice.clone().set_callback({
+        let sender_clone = *sender;
+        let debounce_clone = debounce.clone();
+        let rules_clone = rules.clone();
+        let mut row_clone = rule_row.clone();
+        move |_| {
+            show_rule_channel(&mut row_clone, white_point_from_choice(&rules_clone.white_point_choice, &rules_clone.custom_white_point));
+            request_preview(&sender_clone, &debounce_clone);
+        }//end closure
+    });
+    remove_btn.set_callback({
+        let sender_clone = *sender;
+        let debounce_clone = debounce.clone();
+        let rules_clone = rules.clone();
+        move |_| {
+            let mut rows = rules_clone.rows.borrow_mut();
+            let Some(idx) = rows.iter().position(|r| r.top_row.as_widget_ptr() == top_row.as_widget_ptr()) else {return;};
+            let removed = rows.remove(idx);
+            drop(rows);
+            remove_rule_row(&rules_clone, removed);
+            request_preview(&sender_clone, &debounce_clone);
+        }//end closure
+    });
+
+    // go after the last rule
+    let mut param_flex = rules.param_flex.clone();
+    let last_idx = match rules.rows.borrow().last() {
+        Some(last) => param_flex.find(&last.slider_row),
+        None => param_flex.find(&rules.header_row),
+    };
+    param_flex.insert(&rule_row.top_row, last_idx + 1);
+    param_flex.insert(&rule_row.slider_row, last_idx + 2);
+    param_flex.layout();
+    param_flex.redraw();
+    rules.rows.borrow_mut().push(rule_row);
+    request_preview(sender, debounce);
+}//end add_rule_row()
+
+/// Takes both rows of widgets for row out of the param flex and deletes
+/// them. The row should already be out of the rule rows.
+fn remove_rule_row(rules: &RuleWidgets, row: RuleRow) {
+    let mut param_flex = rules.param_flex.clone();
+    param_flex.remove(&row.top_row);
+    param_flex.remove(&row.slider_row);
+    delete_widget_later(row.top_row);
+    delete_widget_later(row.slider_row);
+    param_flex.layout();
+    param_flex.redraw();
+}//end remove_rule_row()
+
+/// Removes the widgets of every rule
+fn remove_all_rules(rules: &RuleWidgets) {
+    let rows: Vec<RuleRow> = rules.rows.borrow_mut().drain(..).collect();
+    for row in rows {remove_rule_row(rules, row);}
+}//end remove_all_rules()
+
+/// Sends a PreviewThreshold message once there have been no
+/// further requests for PREVIEW_DEBOUNCE_SECS, so dragging a
+/// slider doesn't re-threshold on every pixel of movement.
+fn request_preview(sender: &Sender<InterfaceMessage>, debounce: &Rc<RefCell<PreviewDebounce>>) {
+    let mut debounce_ref = debounce.borrow_mut();
+    debounce_ref.last_change = Instant::now();
+    if debounce_ref.pending {return;}
+    debounce_ref.pending = true;
+    app::add_timeout3(PREVIEW_DEBOUNCE_SECS, {
+        let sender_clone = *sender;
+        let debounce_clone = debounce.clone();
+        move |handle| {
+            let mut debounce_ref = debounce_clone.borrow_mut();
+            let elapsed = debounce_ref.last_change.elapsed().as_secs_f64();
+            if elapsed < PREVIEW_DEBOUNCE_SECS {
+                app::repeat_timeout3(PREVIEW_DEBOUNCE_SECS - elapsed, handle);
+            } else {
+                debounce_ref.pending = false;
+                sender_clone.send(InterfaceMessage::PreviewThreshold);
+            }//end else params have settled
+        }//end closure
+    });
+}//end request_preview()
+
+/// Formats what the inspector shows for the pixel at pos with the rgb
+/// given: whether it passes params and each of their tests, if the params
+/// are valid, and its values in every color space, both on our [0,255]
+/// scale and in native units.
+fn pixel_info_text(pos: (u32,u32), rgb: [u8; 3], params: Option<&ThreshParams>, white_point: WhitePoint) -> String {
+    let verdict = |passes: bool| match passes {
+        true => "passes",
+        false => "fails",
+    };
+    let mut lines = vec![format!("Pixel ({}, {})  RGB {}, {}, {}", pos.0, pos.1, rgb[0], rgb[1], rgb[2])];
+    match params.and_then(|params| Some((params, params.check_pixel(rgb).ok()?))) {
+        None => lines.push("Threshold can't be checked until the expression is fixed".to_string()),
+        Some((params, check)) => {
+            let by_expression = match params.expression {
+                Some(_) => " by the expression",
+                None => "",
+            };
+            lines.push(format!("Threshold {}{by_expression}", verdict(check.passes)));
+            let depths: Vec<String> = params.color_space.channel_names().iter().zip(check.depths)
+                .map(|(name, passes)| format!("{name} {}", verdict(passes)))
+                .collect();
+            lines.push(format!("Depths: {}", depths.join(", ")));
+            if !check.rules.is_empty() {
+                let rules: Vec<String> = check.rules.iter().enumerate()
+                    .map(|(i, passes)| format!("{} {}", i + 1, verdict(*passes)))
+                    .collect();
+                lines.push(format!("Rules: {}", rules.join(", ")));
+            }//end if there are rules to report
+        },
+    }//end matching whether we have valid params
+    lines.push(format!("{:<16}{:<22}{}", "Color Space", "Internal", "Native"));
+    for variant in ColorSpace::get_variants() {
+        let Some(color_space) = ColorSpace::from_str(&variant) else {continue;};
+        let internal = convert_from_rgb_f32(rgb, color_space, white_point).map(|value| format!("{value:>6.1}"));
+        let native = convert_from_rgb_native(rgb, color_space, white_point);
+        let ranges = color_space.native_ranges(white_point);
+        let units = color_space.channel_units();
+        let native: Vec<String> = (0..3)
+            .map(|i| format!("{:>8.*}{:<1}", slider_precision(ranges[i].0, ranges[i].1) as usize, native[i], units[i]))
+            .collect();
+        lines.push(format!("{variant:<16}{:<22}{}", internal.join(" "), native.join(" ")));
+    }//end adding a line for each color space
+    lines.join("\n")
+}//end pixel_info_text()
+
+/// Maps pos in window coordinates to a pixel of the data of img, which
+/// frame draws from its top left at img.w() by img.h() after the aspect
+/// fit of SharedImage::scale(). Positions off the image are moved onto
+/// its nearest edge when clamp is true, or give None otherwise.
+fn frame_to_image(frame: &Frame, img: &SharedImage, pos: (i32,i32), clamp: bool) -> Option<(u32,u32)> {
+    if img.w() <= 0 || img.h() <= 0 {return None;}
+    let x = (pos.0 - frame.x()) as f64 * img.data_w() as f64 / img.w() as f64;
+    let y = (pos.1 - frame.y()) as f64 * img.data_h() as f64 / img.h() as f64;
+    let (x, y) = (x.floor() as i64, y.floor() as i64);
+    let (max_x, max_y) = (img.data_w() as i64 - 1, img.data_h() as i64 - 1);
+    let on_image = (0..=max_x).contains(&x) && (0..=max_y).contains(&y);
+    match (on_image, clamp) {
+        (true, _) | (false, true) => Some((x.clamp(0, max_x) as u32, y.clamp(0, max_y) as u32)),
+        (false, false) => None,
+    }//end matching whether we can give a pixel
+}//end frame_to_image()
+
+/// Gets the region of img that drag picked out on frame. A drag which
+/// barely moves is a click on one pixel, which needs to be on the image.
+fn sample_region(frame: &Frame, img: &SharedImage, drag: &SampleDrag) -> Option<SampleRegion> {
+    let start = *drag.points.first()?;
+    let is_click = drag.points.iter()
+        .all(|(x, y)| (x - start.0).abs() <= SAMPLE_CLICK_DIST && (y - start.1).abs() <= SAMPLE_CLICK_DIST);
+    match (is_click, drag.lasso) {
+        (true, _) => {
+            let (x, y) = frame_to_image(frame, img, start, false)?;
+            Some(SampleRegion::Point(x, y))
+        },
+        (false, false) => Some(SampleRegion::Rect(
+            frame_to_image(frame, img, start, true)?,
+            frame_to_image(frame, img, *drag.points.last()?, true)?,
+        )),
+        (false, true) => Some(SampleRegion::Lasso(drag.points.iter()
+            .filter_map(|pos| frame_to_image(frame, img, *pos, true))
+            .collect())),
+    }//end matching kind of region
+}//end sample_region()
+
+/// Outlines the rectangle or lasso of drag over frame
+fn draw_sample_drag(frame: &Frame, drag: &SampleDrag) {
+    let (Some(first), Some(last)) = (drag.points.first(), drag.points.last()) else {return;};
+    draw::push_clip(frame.x(), frame.y(), frame.w(), frame.h());
+    draw::set_draw_color(Color::Yellow);
+    draw::set_line_style(draw::LineStyle::Dash, 1);
+    match drag.lasso {
+        true => {
+            for pair in drag.points.windows(2) {
+                draw::draw_line(pair[0].0, pair[0].1, pair[1].0, pair[1].1);
+            }//end drawing each segment of the lasso
+            draw::draw_line(last.0, last.1, first.0, first.1);
+        },
+        false => draw::draw_rect(
+            first.0.min(last.0), first.1.min(last.1),
+            (last.0 - first.0).abs() + 1, (last.1 - first.1).abs() + 1,
+        ),
+    }//end matching shape of drag
+    draw::set_line_style(draw::LineStyle::Solid, 0);
+    draw::pop_clip();
+}//end draw_sample_drag()
+
+/// Draws a light and dark gray checkerboard over the area given,
+/// so that transparent parts of an image drawn on top are visible.
+fn draw_checkerboard(x: i32, y: i32, w: i32, h: i32) {
+    draw::push_clip(x, y, w, h);
+    for row in 0..(h + CHECKER_SIZE - 1) / CHECKER_SIZE {
+        for col in 0..(w + CHECKER_SIZE - 1) / CHECKER_SIZE {
+            let color = match (row + col) % 2 {
+                0 => Color::rgb_color(204,204,204),
+                _ => Color::rgb_color(153,153,153),
+            };
+            draw::draw_rect_fill(x + col * CHECKER_SIZE, y + row * CHECKER_SIZE, CHECKER_SIZE, CHECKER_SIZE, color);
+        }//end looping over each checker in row
+    }//end looping over each row of checkers
+    draw::pop_clip();
+}//end draw_checkerboard()
@@ -1,4 +1,6 @@
 pub mod enums;
+pub mod expr;
+pub mod preset;
 pub mod process;
 
 #[cfg(test)]
@@ -1,23 +1,46 @@
-use color_space_threshold_helper::{enums::InterfaceMessage, process::ImageToProc};
+use std::sync::Arc;
+
+use color_space_threshold_helper::{enums::InterfaceMessage, preset::ThreshPreset, process::{ImageToProc, DEFAULT_THRESH_COLOR}};
 use fltk::{enums::ColorDepth, image::{RgbImage, SharedImage}};
 use gui::GUI;
 use image::DynamicImage;
+use worker::ThresholdWorker;
 
 mod gui;
+mod worker;
+
+/// Longest side of the downscaled image used for live previews
+const PREVIEW_MAX_DIM: u32 = 1024;
 
 fn main() {
     println!("Hello world!");
     // set up gui-related variables
     let mut gui = GUI::initialize();
     let recv = gui.get_receiver();
+    let worker = ThresholdWorker::new(gui.get_sender());
     // setup variables to hold data during the program
-    let mut last_img_opened = ImageToProc::blank();
-    let mut last_th_color: (u8,u8,u8) = (255,0,0);
+    let mut last_img_opened = Arc::new(ImageToProc::blank());
+    let mut last_preview_img = Arc::new(ImageToProc::blank());
+    let mut last_th_color: (u8,u8,u8) = DEFAULT_THRESH_COLOR;
+    let mut last_th_img: Option<DynamicImage> = None;
     // do the main application loop
     while gui.wait() {
         match recv.recv() {
             Some(InterfaceMessage::Quit) => gui.quit(),
-            Some(InterfaceMessage::Reset) => println!("Reset not yet supported"),
+            Some(InterfaceMessage::Reset) => {
+                worker.cancel();
+                gui.set_progress(0., "");
+                gui.reset_params();
+                gui.update_histograms(&last_preview_img);
+                last_th_color = DEFAULT_THRESH_COLOR;
+                gui.set_thresh_color(last_th_color);
+                last_th_img = None;
+                match dynamic_img_to_shared_img(last_img_opened.get_image()) {
+                    Err(err) => println!("Couldn't restore original image because: {err}"),
+                    Ok(shared_img) => gui.load_image(shared_img)
+                        .unwrap_or_else(|e| println!("Couldn't load image because of BorrowMutError: {:?}",e))
+                }//end matching whether we can convert the original image to a shared image
+            },
             Some(InterfaceMessage::LoadImage(img_path)) => {
                 println!("Got told to load an image at {}", img_path.to_string_lossy());
                 match ImageToProc::read_image(img_path) {
@@ -27,26 +50,135 @@ fn main() {
                             Err(err) => println!("{err}"),
                             Ok(shared_img) => match gui.load_image(shared_img) {
                                 Err(err) => println!("Couldn't load image because of BorrowMutError: {:?}",err),
-                                Ok(_) => last_img_opened = img,
+                                Ok(_) => {
+                                    worker.cancel();
+                                    gui.set_progress(0., "");
+                                    last_preview_img = Arc::new(img.preview(PREVIEW_MAX_DIM));
+                                    last_img_opened = Arc::new(img);
+                                    last_th_img = None;
+                                    gui.update_histograms(&last_preview_img);
+                                },
                             }//end case that we can convert dynamic to shared image, try loading it
                         }//end matching whether we can convert from dynamic to shared image
                     },//end case that we can read image from path
                 }//end matching whether we can read image from path
             },
-            Some(InterfaceMessage::ThreshColor(color)) => last_th_color = color,
+            Some(InterfaceMessage::ThreshColor(color)) => {
+                last_th_color = color;
+                gui.request_preview();
+            },
+            Some(InterfaceMessage::PreviewThreshold) => {
+                // the params changed, so the last full threshold no longer matches what's shown
+                last_th_img = None;
+                // no color space chosen yet is normal here, so just skip the preview
+                if let Some(th_params) = gui.get_thresh_params() {
+                    worker.start(last_preview_img.clone(), th_params, last_th_color, gui.get_output_mode(), true);
+                }//end if we can get thresholding parameters
+            },
             Some(InterfaceMessage::DoThreshold) => {
                 match gui.get_thresh_params() {
                     None => println!("Couldn't get threshold parameters."),
                     Some(th_params) => {
-                        let th_img = last_img_opened.threshold_img(th_params, last_th_color);
-                        match dynamic_img_to_shared_img(&th_img) {
-                            Err(err) => println!("Couldn't load thresholded image because: {err}"),
-                            Ok(shared_image) => gui.load_image(shared_image)
-                                .unwrap_or_else(|e| println!("Couldn't load image because of BorrowMutError: {:?}",e))
-                        }//end matching whether we can convert the dynamic image to a shared image
+                        worker.start(last_img_opened.clone(), th_params, last_th_color, gui.get_output_mode(), false);
+                        gui.set_progress(0., "Thresholding");
                     },//end case that we can get the thresholding parameters 
                 }//end matching whether we can get thresholding parameters
             },
+            Some(InterfaceMessage::ThresholdProgress(job_id, percent)) if job_id == worker.latest_job() => {
+                gui.set_progress(percent as f64, &format!("{percent}%"));
+            },
+            // progress for a job we no longer want
+            Some(InterfaceMessage::ThresholdProgress(..)) => (),
+            Some(InterfaceMessage::ThresholdDone(job_id)) => {
+                if let Some(th_result) = worker.take_result(job_id) {
+                    gui.set_progress(100., "Done");
+                    match dynamic_img_to_shared_img(&th_result.img) {
+                        Err(err) => println!("Couldn't load thresholded image because: {err}"),
+                        Ok(shared_image) => gui.load_image(shared_image)
+                            .unwrap_or_else(|e| println!("Couldn't load image because of BorrowMutError: {:?}",e))
+                    }//end matching whether we can convert the dynamic image to a shared image
+                    if !th_result.is_preview {last_th_img = Some(th_result.img);}
+                }//end if the finished job is still the one we want
+            },
+            Some(InterfaceMessage::SaveImage(img_path)) => {
+                match &last_th_img {
+                    None => println!("No thresholded image for the current parameters to save yet."),
+                    Some(th_img) => match ImageToProc::save_image(th_img, &img_path) {
+                        Err(err) => println!("Couldn't save image because ImageError: {:?}", err),
+                        Ok(_) => println!("Saved thresholded image to {}", img_path.to_string_lossy()),
+                    }//end matching whether we could save the image
+                }//end matching whether we have a thresholded image
+            },
+            Some(InterfaceMessage::SavePreset(preset_path)) => {
+                match gui.get_thresh_params() {
+                    None => println!("Couldn't get threshold parameters."),
+                    Some(th_params) => match ThreshPreset::new(th_params, last_th_color).write_preset(&preset_path) {
+                        Err(err) => println!("{err}"),
+                        Ok(_) => println!("Saved threshold preset to {}", preset_path.to_string_lossy()),
+                    }//end matching whether we could write the preset
+                }//end matching whether we can get thresholding parameters
+            },
+            Some(InterfaceMessage::LoadPreset(preset_path)) => {
+                match ThreshPreset::read_preset(&preset_path) {
+                    Err(err) => println!("{err}"),
+                    Ok(preset) => {
+                        gui.set_thresh_params(&preset.params);
+                        gui.update_histograms(&last_preview_img);
+                        last_th_color = preset.thresh_color;
+                        gui.set_thresh_color(last_th_color);
+                        gui.request_preview();
+                    },
+                }//end matching whether we could read the preset
+            },
+            Some(InterfaceMessage::ExportImageJ) => {
+                match gui.get_thresh_params() {
+                    None => println!("Couldn't get threshold parameters."),
+                    Some(th_params) => match th_params.to_imagej_macro() {
+                        Err(err) => println!("Couldn't export ImageJ macro because: {err}"),
+                        Ok(ij_macro) => {
+                            println!("{ij_macro}");
+                            gui.copy_to_clipboard(&ij_macro);
+                            println!("Copied ImageJ macro to clipboard.");
+                        },
+                    }//end matching whether we could export the macro
+                }//end matching whether we can get thresholding parameters
+            },
+            Some(InterfaceMessage::ExportOpenCV) => {
+                match gui.get_thresh_params() {
+                    None => println!("Couldn't get threshold parameters."),
+                    Some(th_params) => match th_params.to_opencv_bounds() {
+                        Err(err) => println!("Couldn't export OpenCV bounds because: {err}"),
+                        Ok(cv_bounds) => {
+                            for warning in cv_bounds.warnings.iter() {println!("Warning: {warning}");}
+                            let cv_python = cv_bounds.to_python();
+                            println!("{cv_python}");
+                            gui.copy_to_clipboard(&cv_python);
+                            println!("Copied OpenCV bounds to clipboard.");
+                        },
+                    }//end matching whether we could export the bounds
+                }//end matching whether we can get thresholding parameters
+            },
+            Some(InterfaceMessage::ExpressionFromSliders) => {
+                gui.expression_from_sliders();
+                gui.request_preview();
+            },
+            Some(InterfaceMessage::UpdateHistograms) => gui.update_histograms(&last_preview_img),
+            Some(InterfaceMessage::InspectPixel(pos, shown_size)) => {
+                let pos = last_img_opened.scale_point(pos, shown_size);
+                if let Some((pos, rgb)) = pos.and_then(|pos| Some((pos, last_img_opened.rgb_at(pos)?))) {
+                    gui.inspect_pixel(pos, rgb);
+                }//end if mouse is over a pixel of the original image
+            },
+            Some(InterfaceMessage::SamplePixels(region, picked_size)) => {
+                let pixels = last_img_opened.sample_pixels(&region, picked_size);
+                match pixels.is_empty() {
+                    true => println!("No pixels to sample in {:?}", region),
+                    false => {
+                        gui.apply_samples(&pixels);
+                        gui.request_preview();
+                    },
+                }//end matching whether we sampled any pixels
+            },
             None => (),
         }//end matching message received
     }//end main app loop
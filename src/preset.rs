@@ -0,0 +1,50 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::process::{ThreshParams, DEFAULT_THRESH_COLOR};
+
+/// Everything needed to repeat a threshold later, stored
+/// in a human-readable toml file.
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+pub struct ThreshPreset {
+    /// The rgb color used to mark pixels outside the threshold
+    #[serde(default = "default_thresh_color")]
+    pub thresh_color: (u8,u8,u8),
+    pub params: ThreshParams,
+}//end struct ThreshPreset
+
+fn default_thresh_color() -> (u8,u8,u8) {DEFAULT_THRESH_COLOR}
+
+impl ThreshPreset {
+    pub fn new(params: ThreshParams, thresh_color: (u8,u8,u8)) -> ThreshPreset {
+        ThreshPreset { thresh_color, params }
+    }//end new()
+
+    /// Attempts to read a preset from the toml file at path
+    pub fn read_preset(path: &Path) -> Result<ThreshPreset, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Couldn't read preset file {} because: {err}", path.to_string_lossy()))?;
+        ThreshPreset::from_toml(&contents)
+    }//end read_preset()
+
+    /// Attempts to write this preset as toml to the file at path
+    pub fn write_preset(&self, path: &Path) -> Result<(), String> {
+        let contents = self.to_toml()?;
+        fs::write(path, contents)
+            .map_err(|err| format!("Couldn't write preset file {} because: {err}", path.to_string_lossy()))
+    }//end write_preset()
+
+    /// Parses a preset from toml text, failing if its expression
+    /// doesn't parse or a rule has a channel that doesn't exist
+    pub fn from_toml(contents: &str) -> Result<ThreshPreset, String> {
+        let preset: ThreshPreset = toml::from_str(contents).map_err(|err| format!("Couldn't parse preset because: {err}"))?;
+        preset.params.to_expr().map_err(|err| format!("Preset has an invalid threshold because: {err}"))?;
+        Ok(preset)
+    }//end from_toml()
+
+    /// Formats this preset as toml text
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|err| format!("Couldn't format preset because: {err}"))
+    }//end to_toml()
+}//end impl for ThreshPreset
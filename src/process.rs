@@ -1,28 +1,626 @@
 use core::f64;
 use std::path::PathBuf;
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex};
 
 // use color_space::{Cmy, FromRgb, Hsl, Hsv, HunterLab, Lab, Lch, Luv, Rgb, Xyz, Yxy};
-use image::{ColorType, DynamicImage, GenericImage, GenericImageView, ImageError, ImageReader};
-use palette::{FromColor, GetHue, Hsl, Hsv, Lab, Srgb};
+use image::{ColorType, DynamicImage, GenericImage, GenericImageView, GrayImage, ImageError, ImageFormat, ImageReader, RgbaImage};
+use palette::{FromColor, GetHue, Hsl, Hsv, Oklab, Oklch, Srgb, Xyz, Yxy};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::enums::ColorSpace;
+use crate::enums::{ColorSpace, OutputMode, SamplePadding, SampleRegion, WhitePoint};
+use crate::expr::ThreshExpr;
 
+/// The color used to mark pixels outside the threshold until the user picks another.
+pub const DEFAULT_THRESH_COLOR: (u8,u8,u8) = (255,0,0);
+
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
 pub struct ThreshParams {
     pub color_space: ColorSpace,
     
-    pub depth1_min: u8,
-    pub depth1_max: u8,
+    pub depth1_min: f32,
+    pub depth1_max: f32,
     pub depth1_pass: bool,
     
-    pub depth2_min: u8,
-    pub depth2_max: u8,
+    pub depth2_min: f32,
+    pub depth2_max: f32,
     pub depth2_pass: bool,
 
-    pub depth3_min: u8,
-    pub depth3_max: u8,
+    pub depth3_min: f32,
+    pub depth3_max: f32,
     pub depth3_pass: bool,
+
+    /// The reference white for Lab, LCh, Luv and XYZ.
+    /// Presets from before this existed were all D65.
+    #[serde(default)]
+    pub white_point: WhitePoint,
+
+    /// More (min, max, pass) intervals for each depth, on top of the
+    /// one above. A value gets through a depth if any interval lets it.
+    /// Presets from before this existed had one interval per depth.
+    #[serde(default)]
+    pub extra_intervals: [Vec<(f32,f32,bool)>; 3],
+
+    /// Expression text, in native units, which replaces the depths
+    /// above when given. See ThreshExpr::parse() for the syntax.
+    #[serde(default)]
+    pub expression: Option<String>,
+
+    /// Intervals on channels of any color space, which pixels need
+    /// to get through on top of the depths or expression
+    #[serde(default)]
+    pub rules: Vec<ThreshRule>,
 }//end struct ThreshParams
 
+/// Gets the (min, max) on our [0,255] scale which holds every one of
+/// values, which can't be empty, widened by padding. On a circular
+/// channel, the range starts just past the biggest gap between values,
+/// and can come out with min above max when it goes through 0.
+fn sample_bounds(mut values: Vec<f32>, circular: bool, padding: SamplePadding) -> (f32, f32) {
+    values.sort_by(f32::total_cmp);
+    let (lowest, highest) = (values[0], values[values.len() - 1]);
+    let start = match circular {
+        false => lowest,
+        true => {
+            // the gap from the highest value back around to the lowest
+            let mut biggest_gap = (lowest + 255. - highest, lowest);
+            for pair in values.windows(2) {
+                if pair[1] - pair[0] > biggest_gap.0 {biggest_gap = (pair[1] - pair[0], pair[1]);}
+            }//end looking at the gap after each value
+            biggest_gap.1
+        },
+    };
+    // values as distances past start, so circular ranges don't wrap
+    let offsets: Vec<f32> = values.iter()
+        .map(|value| match circular {
+            true => (value - start).rem_euclid(255.),
+            false => value - start,
+        })
+        .collect();
+    let span = offsets.iter().copied().fold(0f32, f32::max);
+    let pad = match padding {
+        SamplePadding::Percent(percent) => percent / 100. * 255.,
+        SamplePadding::StdDevs(count) => {
+            let mean = offsets.iter().sum::<f32>() / offsets.len() as f32;
+            let variance = offsets.iter().map(|offset| (offset - mean).powi(2)).sum::<f32>() / offsets.len() as f32;
+            count * variance.sqrt()
+        },
+    };
+    let (min, max) = (start - pad, start + span + pad);
+    match circular {
+        true if max - min >= 255. => (0., 255.),
+        true => (min.rem_euclid(255.), max.rem_euclid(255.)),
+        false => (min.clamp(0., 255.), max.clamp(0., 255.)),
+    }//end matching whether we need to wrap or clamp
+}//end sample_bounds()
+
+/// How one pixel fares against a threshold
+#[derive(Clone,Debug,PartialEq)]
+pub struct PixelCheck {
+    /// Whether the pixel gets through any interval of each depth
+    pub depths: [bool; 3],
+    /// Whether the pixel gets through each rule
+    pub rules: Vec<bool>,
+    /// Whether the pixel passes the whole threshold, which follows
+    /// the expression instead of the depths when there is one
+    pub passes: bool,
+}//end struct PixelCheck
+
+/// A (min, max, pass) interval on one channel of a color space, which
+/// may differ from the color space the rest of the threshold is in
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+pub struct ThreshRule {
+    pub color_space: ColorSpace,
+    /// The index of the channel, from 0 to 2
+    pub channel: usize,
+    pub min: f32,
+    pub max: f32,
+    pub pass: bool,
+}//end struct ThreshRule
+
+impl ThreshRule {
+    /// Builds a rule from min and max in the native units of the channel
+    pub fn from_native(color_space: ColorSpace, channel: usize, white_point: WhitePoint, (min, max, pass): (f32,f32,bool)) -> ThreshRule {
+        ThreshRule {
+            color_space,
+            channel,
+            min: color_space.to_internal(channel, min, white_point),
+            max: color_space.to_internal(channel, max, white_point),
+            pass,
+        }//end struct construction
+    }//end from_native()
+
+    /// Gets the (min, max, pass) of this rule in the native units of the channel
+    pub fn native_interval(&self, white_point: WhitePoint) -> (f32,f32,bool) {
+        (
+            self.color_space.to_native(self.channel, self.min, white_point),
+            self.color_space.to_native(self.channel, self.max, white_point),
+            self.pass,
+        )
+    }//end native_interval()
+}//end impl for ThreshRule
+
+impl ThreshParams {
+    /// Gets the (min, max, pass) of each depth as an array
+    pub fn depths(&self) -> [(f32,f32,bool); 3] {
+        [
+            (self.depth1_min, self.depth1_max, self.depth1_pass),
+            (self.depth2_min, self.depth2_max, self.depth2_pass),
+            (self.depth3_min, self.depth3_max, self.depth3_pass),
+        ]
+    }//end depths()
+
+    /// Builds params from the (min, max, pass) of each depth
+    pub fn from_depths(color_space: ColorSpace, white_point: WhitePoint, depths: [(f32,f32,bool); 3]) -> ThreshParams {
+        ThreshParams {
+            color_space,
+            depth1_min: depths[0].0,
+            depth1_max: depths[0].1,
+            depth1_pass: depths[0].2,
+            depth2_min: depths[1].0,
+            depth2_max: depths[1].1,
+            depth2_pass: depths[1].2,
+            depth3_min: depths[2].0,
+            depth3_max: depths[2].1,
+            depth3_pass: depths[2].2,
+            white_point,
+            extra_intervals: Default::default(),
+            expression: None,
+            rules: Vec::new(),
+        }//end struct construction
+    }//end from_depths()
+
+    /// Gets the (min, max, pass) of each depth in the native units
+    /// of the color space, such as degrees of hue
+    pub fn native_depths(&self) -> [(f32,f32,bool); 3] {
+        let mut depths = self.depths();
+        for (i, (min, max, _)) in depths.iter_mut().enumerate() {
+            *min = self.color_space.to_native(i, *min, self.white_point);
+            *max = self.color_space.to_native(i, *max, self.white_point);
+        }//end converting bounds of each depth
+        depths
+    }//end native_depths()
+
+    /// Builds params from the (min, max, pass) of each depth
+    /// given in the native units of color_space
+    pub fn from_native_depths(color_space: ColorSpace, white_point: WhitePoint, mut depths: [(f32,f32,bool); 3]) -> ThreshParams {
+        for (i, (min, max, _)) in depths.iter_mut().enumerate() {
+            *min = color_space.to_internal(i, *min, white_point);
+            *max = color_space.to_internal(i, *max, white_point);
+        }//end converting bounds of each depth
+        ThreshParams::from_depths(color_space, white_point, depths)
+    }//end from_native_depths()
+
+    /// Gets every (min, max, pass) interval of each depth,
+    /// starting with the one from depths()
+    pub fn intervals(&self) -> [Vec<(f32,f32,bool)>; 3] {
+        let depths = self.depths();
+        [0,1,2].map(|i| {
+            let mut intervals = vec![depths[i]];
+            intervals.extend_from_slice(&self.extra_intervals[i]);
+            intervals
+        })
+    }//end intervals()
+
+    /// Builds params from the (min, max, pass) intervals of each depth.
+    /// The first interval of a depth goes in its min, max and pass, and
+    /// a depth without any intervals lets everything through.
+    pub fn from_intervals(color_space: ColorSpace, white_point: WhitePoint, intervals: [Vec<(f32,f32,bool)>; 3]) -> ThreshParams {
+        let mut extra_intervals: [Vec<(f32,f32,bool)>; 3] = Default::default();
+        let mut depths = [(0.,255.,true); 3];
+        for (i, channel_intervals) in intervals.into_iter().enumerate() {
+            let mut channel_intervals = channel_intervals.into_iter();
+            if let Some(first) = channel_intervals.next() {depths[i] = first;}
+            extra_intervals[i] = channel_intervals.collect();
+        }//end splitting up intervals of each depth
+        ThreshParams {
+            extra_intervals,
+            ..ThreshParams::from_depths(color_space, white_point, depths)
+        }//end struct construction
+    }//end from_intervals()
+
+    /// Gets every (min, max, pass) interval of each depth in the
+    /// native units of the color space
+    pub fn native_intervals(&self) -> [Vec<(f32,f32,bool)>; 3] {
+        let mut intervals = self.intervals();
+        for (i, channel_intervals) in intervals.iter_mut().enumerate() {
+            for (min, max, _) in channel_intervals.iter_mut() {
+                *min = self.color_space.to_native(i, *min, self.white_point);
+                *max = self.color_space.to_native(i, *max, self.white_point);
+            }//end converting bounds of each interval
+        }//end converting intervals of each depth
+        intervals
+    }//end native_intervals()
+
+    /// Builds params from the (min, max, pass) intervals of each
+    /// depth given in the native units of color_space
+    pub fn from_native_intervals(color_space: ColorSpace, white_point: WhitePoint, mut intervals: [Vec<(f32,f32,bool)>; 3]) -> ThreshParams {
+        for (i, channel_intervals) in intervals.iter_mut().enumerate() {
+            for (min, max, _) in channel_intervals.iter_mut() {
+                *min = color_space.to_internal(i, *min, white_point);
+                *max = color_space.to_internal(i, *max, white_point);
+            }//end converting bounds of each interval
+        }//end converting intervals of each depth
+        ThreshParams::from_intervals(color_space, white_point, intervals)
+    }//end from_native_intervals()
+
+    /// Builds params whose depths pass from the lowest to the highest
+    /// value of pixels in color_space, widened by padding. Hue goes the
+    /// short way around, so reds on either side of 0 give a narrow range
+    /// which wraps. Gives None if there are no pixels.
+    pub fn from_samples(color_space: ColorSpace, white_point: WhitePoint, pixels: &[[u8; 3]], padding: SamplePadding) -> Option<ThreshParams> {
+        if pixels.is_empty() {return None;}
+        let converted: Vec<[f32; 3]> = pixels.iter()
+            .map(|rgb| convert_from_rgb_f32(*rgb, color_space, white_point))
+            .collect();
+        let hue_idx = color_space.hue_channel();
+        let depths = [0, 1, 2].map(|channel| {
+            let values = converted.iter().map(|values| values[channel]).collect();
+            let (min, max) = sample_bounds(values, hue_idx == Some(channel), padding);
+            (min, max, true)
+        });
+        Some(ThreshParams::from_depths(color_space, white_point, depths))
+    }//end from_samples()
+
+    /// Returns true if any depth has more than one interval
+    pub fn has_extra_intervals(&self) -> bool {
+        self.extra_intervals.iter().any(|intervals| !intervals.is_empty())
+    }//end has_extra_intervals()
+
+    /// Builds the expression that the depths stand for, where every
+    /// depth needs to pass, by getting through any of its intervals.
+    pub fn depths_expr(&self) -> ThreshExpr {
+        let channel_exprs = self.intervals().into_iter().enumerate().filter_map(|(channel, intervals)| {
+            ThreshExpr::any(intervals.into_iter().map(|interval| ThreshExpr::interval(self.color_space, channel, interval)))
+        });
+        ThreshExpr::all(channel_exprs).expect("There are always three depths")
+    }//end depths_expr()
+
+    /// Returns true if values, in the color space of these params, get
+    /// through every depth by getting through any of its intervals.
+    /// This gives the same as matching depths_expr(), without the tree.
+    pub fn depths_pass(&self, values: [f32; 3]) -> bool {
+        let hue_idx = self.color_space.hue_channel();
+        self.depths().into_iter().enumerate().all(|(i, (min, max, pass))| {
+            let circular = hue_idx == Some(i);
+            ThreshParams::depth_passes(values[i], min, max, pass, circular)
+                || self.extra_intervals[i].iter().any(|&(min, max, pass)| ThreshParams::depth_passes(values[i], min, max, pass, circular))
+        })
+    }//end depths_pass()
+
+    /// Gets the expression to threshold with, parsing the expression
+    /// text if there is some, or building it from the depths otherwise,
+    /// and then requiring every rule to pass as well.
+    pub fn to_expr(&self) -> Result<ThreshExpr, String> {
+        let base = match &self.expression {
+            Some(text) => ThreshExpr::parse(text, self.color_space, self.white_point)?,
+            None => self.depths_expr(),
+        };
+        let mut rule_exprs = Vec::new();
+        for rule in self.rules.iter() {
+            if rule.channel > 2 {
+                return Err(format!("{:?} has no channel {}, only 0 to 2", rule.color_space, rule.channel));
+            }//end if rule's channel doesn't exist
+            rule_exprs.push(ThreshExpr::interval(rule.color_space, rule.channel, (rule.min, rule.max, rule.pass)));
+        }//end building the expression for each rule
+        Ok(ThreshExpr::all(std::iter::once(base).chain(rule_exprs)).expect("There's always a base expression"))
+    }//end to_expr()
+
+    /// Checks how rgb fares against these params, converting it into
+    /// each color space the depths, expression and rules need.
+    /// Gives the error from to_expr() if the params aren't valid.
+    pub fn check_pixel(&self, rgb: [u8; 3]) -> Result<PixelCheck, String> {
+        let expr = self.to_expr()?;
+        let values = |space: ColorSpace| convert_from_rgb_f32(rgb, space, self.white_point);
+        let depths = [0, 1, 2].map(|channel| self.intervals()[channel].iter()
+            .any(|interval| ThreshExpr::interval(self.color_space, channel, *interval).matches(&values)));
+        let rules = self.rules.iter()
+            .map(|rule| ThreshExpr::interval(rule.color_space, rule.channel, (rule.min, rule.max, rule.pass)).matches(&values))
+            .collect();
+        let passes = expr.matches(&values);
+        Ok(PixelCheck { depths, rules, passes })
+    }//end check_pixel()
+
+    /// Returns true if value gets through a depth with the bounds given.
+    /// Circular depths, like hue, go around from min to max, so a min
+    /// of 240 degrees and max of 20 passes through 0, and stopping lets
+    /// through exactly what passing with the same bounds would not.
+    /// Other depths pass values from min to max, and stop only lets
+    /// through values between max and min.
+    pub fn depth_passes(value: f32, min: f32, max: f32, pass: bool, circular: bool) -> bool {
+        match (circular, pass) {
+            (false, true) => value >= min && value <= max,
+            (false, false) => value <= min && value >= max,
+            (true, _) => {
+                let in_arc = match min <= max {
+                    true => value >= min && value <= max,
+                    false => value >= min || value <= max,
+                };
+                in_arc == pass
+            },
+        }//end matching how the depth limits values
+    }//end depth_passes()
+
+    /// Gets the values that a depth lets through as a DepthRange,
+    /// or None if that can't be expressed as one.
+    /// Our stop on a non-circular depth only lets through values
+    /// between max and min, which is the same as passing with the
+    /// bounds swapped, and lets nothing through when min is below max.
+    /// Circular depths follow depth_passes(), so the range is widened
+    /// to include its bounds where they would be left out.
+    pub fn pass_range(min: f32, max: f32, pass: bool, circular: bool) -> Option<DepthRange> {
+        match (circular, pass, min <= max) {
+            (false, true, _) => Some(DepthRange::Inside(min, max)),
+            (false, false, _) if max <= min => Some(DepthRange::Inside(max, min)),
+            (false, false, _) => None,
+            (true, true, true) => Some(DepthRange::Inside(min, max)),
+            (true, true, false) => Some(DepthRange::Outside(max, min)),
+            (true, false, true) => Some(DepthRange::Outside(min, max)),
+            (true, false, false) => Some(DepthRange::Inside(max, min)),
+        }//end matching how the depth limits values
+    }//end pass_range()
+
+    /// Formats these parameters as an ImageJ/Fiji macro which
+    /// repeats the threshold with the same steps as the macro
+    /// recorded by ImageJ's Color Threshold dialog.
+    /// ImageJ stores RGB, HSB and Lab L in the same [0,255] scaling
+    /// we use, so those min and max values carry over, while it stores
+    /// Lab a and b as a + 128, so those are converted. Values are widened
+    /// to the nearest whole numbers since ImageJ's channels are 8-bit.
+    pub fn to_imagej_macro(&self) -> Result<String, String> {
+        if self.color_space == ColorSpace::LabCIE && self.white_point != WhitePoint::D65 {
+            return Err(format!("ImageJ's Lab conversion only uses a D65 white point, not {:?}", self.white_point));
+        }//end if imagej would compute a different Lab
+        if self.has_extra_intervals() {
+            return Err("ImageJ's Color Threshold only takes one range per channel".to_string());
+        }//end if imagej can't hold every interval
+        if self.expression.is_some() {
+            return Err("ImageJ's Color Threshold can't follow a threshold expression".to_string());
+        }//end if imagej can't follow the expression
+        if !self.rules.is_empty() {
+            return Err("ImageJ's Color Threshold can't follow rules in other color spaces".to_string());
+        }//end if imagej can't follow the rules
+        let (space_cmd, window_names) = match self.color_space {
+            ColorSpace::RGB => ("run(\"RGB Stack\");", ["Red","Green","Blue"]),
+            ColorSpace::HSBorHSV => ("run(\"HSB Stack\");", ["Hue","Saturation","Brightness"]),
+            ColorSpace::LabCIE => ("call(\"ij.plugin.frame.ColorThresholder.RGBtoLab\");\nrun(\"RGB Stack\");", ["Red","Green","Blue"]),
+            other => return Err(format!("ImageJ's Color Threshold doesn't support the {:?} color space", other)),
+        };
+
+        let mut lines: Vec<String> = vec![
+            "// Color Thresholder 2.x".to_string(),
+            format!("// Exported from Color Space Threshold Helper, color space: {:?}", self.color_space),
+            "min=newArray(3);".to_string(),
+            "max=newArray(3);".to_string(),
+            "filter=newArray(3);".to_string(),
+            "a=getTitle();".to_string(),
+            space_cmd.to_string(),
+            "run(\"Convert Stack to Images\");".to_string(),
+        ];
+        for (i, name) in window_names.iter().enumerate() {
+            lines.push(format!("selectWindow(\"{name}\");"));
+            lines.push(format!("rename(\"{i}\");"));
+        }//end renaming each channel window
+        let hue_idx = self.color_space.hue_channel();
+        for (i, (min, max, pass)) in self.depths().into_iter().enumerate() {
+            let range = ThreshParams::pass_range(min, max, pass, hue_idx == Some(i))
+                .ok_or_else(|| format!("Depth {} stops with min {min} below max {max}, which nothing passes", i + 1))?;
+            let is_lab_ab = self.color_space == ColorSpace::LabCIE && i > 0;
+            let to_ij = |v: f32| match is_lab_ab {
+                true => self.color_space.to_native(i, v, self.white_point) + 128.,
+                false => v,
+            };
+            // imagej's stop lets through everything outside min to max
+            let (min, max, filter) = match range {
+                DepthRange::Inside(lower, upper) => {
+                    let (min, max) = widen_to_u8(to_ij(lower), to_ij(upper));
+                    (min, max, "pass")
+                },
+                DepthRange::Outside(below, above) => match outside_gap_u8(to_ij(below), to_ij(above)) {
+                    Some((min, max)) => (min, max, "stop"),
+                    None => (0, 255, "pass"),
+                },
+            };
+            lines.push(format!("min[{i}]={min};"));
+            lines.push(format!("max[{i}]={max};"));
+            lines.push(format!("filter[{i}]=\"{filter}\";"));
+        }//end adding bounds of each channel
+        lines.extend([
+            "for (i=0;i<3;i++){",
+            "  selectWindow(\"\"+i);",
+            "  setThreshold(min[i], max[i]);",
+            "  run(\"Convert to Mask\");",
+            "  if (filter[i]==\"stop\")  run(\"Invert\");",
+            "}",
+            "imageCalculator(\"AND create\", \"0\",\"1\");",
+            "imageCalculator(\"AND create\", \"Result of 0\",\"2\");",
+            "for (i=0;i<3;i++){",
+            "  selectWindow(\"\"+i);",
+            "  close();",
+            "}",
+            "selectWindow(\"Result of 0\");",
+            "close();",
+            "selectWindow(\"Result of Result of 0\");",
+            "rename(a);",
+            "// Colour Thresholding-------------",
+        ].iter().map(|s| s.to_string()));
+
+        Ok(lines.join("\n"))
+    }//end to_imagej_macro()
+
+    /// Converts these parameters into lower and upper bounds for OpenCV's
+    /// cv2.inRange(), in the channel order and scaling of the matching
+    /// cv2.cvtColor() conversion from BGR.
+    /// OpenCV stores 8-bit hue as degrees / 2, so hue bounds are rescaled
+    /// to [0,179], and 8-bit Lab a and b as a + 128, so those are shifted.
+    /// Other channels carry over, widened to the nearest whole numbers.
+    /// Hue ranges which wrap around through 0 get a lower bound above
+    /// their upper bound, see OpenCvBounds.
+    pub fn to_opencv_bounds(&self) -> Result<OpenCvBounds, String> {
+        if self.color_space == ColorSpace::LabCIE && self.white_point != WhitePoint::D65 {
+            return Err(format!("OpenCV's Lab conversion only uses a D65 white point, not {:?}", self.white_point));
+        }//end if opencv would compute a different Lab
+        if self.has_extra_intervals() {
+            return Err("OpenCV's inRange only takes one range per channel".to_string());
+        }//end if inrange can't hold every interval
+        if self.expression.is_some() {
+            return Err("OpenCV's inRange can't follow a threshold expression".to_string());
+        }//end if inrange can't follow the expression
+        if !self.rules.is_empty() {
+            return Err("OpenCV's inRange can't follow rules in other color spaces".to_string());
+        }//end if inrange can't follow the rules
+        // for each opencv channel, the index of our depth that goes there
+        let (conversion, order, hue_idx) = match self.color_space {
+            ColorSpace::RGB => (None, [2,1,0], None),
+            ColorSpace::HSBorHSV => (Some("cv2.COLOR_BGR2HSV"), [0,1,2], Some(0)),
+            ColorSpace::HSL => (Some("cv2.COLOR_BGR2HLS"), [0,2,1], Some(0)),
+            // a and b are shifted below
+            ColorSpace::LabCIE => (Some("cv2.COLOR_BGR2Lab"), [0,1,2], None),
+            // our YUV has the same coefficients as opencv's YCrCb
+            ColorSpace::YUV => (Some("cv2.COLOR_BGR2YCrCb"), [0,2,1], None),
+            // opencv's YCrCb is full range BT.601
+            ColorSpace::YCbCr601 => (Some("cv2.COLOR_BGR2YCrCb"), [0,2,1], None),
+            other => return Err(format!("OpenCV doesn't have a conversion for the {:?} color space", other)),
+        };
+
+        let depths = self.depths();
+        let channel_names = self.color_space.channel_names();
+        let mut bounds = OpenCvBounds {
+            color_space: self.color_space,
+            conversion,
+            lower: [0; 3],
+            upper: [255; 3],
+            warnings: Vec::new(),
+        };
+        for (cv_idx, depth_idx) in order.into_iter().enumerate() {
+            let (min, max, pass) = depths[depth_idx];
+            let is_hue = hue_idx == Some(depth_idx);
+            let range = match ThreshParams::pass_range(min, max, pass, is_hue) {
+                Some(range) => range,
+                None => {
+                    bounds.warnings.push(format!(
+                        "{} stops with min {min} and max {max}, which can't be expressed as a single range, so it is left unbounded",
+                        channel_names[depth_idx]
+                    ));
+                    DepthRange::Inside(0., 255.)
+                },
+            };
+            let to_cv_hue = |v: f32| v as f64 * 180. / 255.;
+            let (lower, upper) = match range {
+                DepthRange::Inside(lower, upper) => (lower, upper),
+                // only hue is circular, so only hue can pass outside a range
+                DepthRange::Outside(below, above) => {
+                    let low_arc_end = to_cv_hue(below).ceil().min(179.);
+                    let high_arc_start = to_cv_hue(above).floor().min(179.);
+                    match low_arc_end + 1. < high_arc_start {
+                        true => {
+                            bounds.lower[cv_idx] = high_arc_start as u8;
+                            bounds.upper[cv_idx] = low_arc_end as u8;
+                        },
+                        false => {
+                            bounds.lower[cv_idx] = 0;
+                            bounds.upper[cv_idx] = 179;
+                        },
+                    }//end matching whether any hue is left out
+                    continue;
+                },
+            };
+            let is_lab_ab = self.color_space == ColorSpace::LabCIE && depth_idx > 0;
+            match (is_hue, is_lab_ab) {
+                (true, _) => {
+                    bounds.lower[cv_idx] = to_cv_hue(lower).floor().min(179.) as u8;
+                    bounds.upper[cv_idx] = to_cv_hue(upper).ceil().min(179.) as u8;
+                },
+                (false, true) => {
+                    let native = |v: f32| self.color_space.to_native(depth_idx, v, self.white_point) + 128.;
+                    (bounds.lower[cv_idx], bounds.upper[cv_idx]) = widen_to_u8(native(lower), native(upper));
+                },
+                (false, false) => {
+                    (bounds.lower[cv_idx], bounds.upper[cv_idx]) = widen_to_u8(lower, upper);
+                },
+            }//end matching whether we need to rescale hue or lab
+        }//end converting the bounds of each channel
+
+        Ok(bounds)
+    }//end to_opencv_bounds()
+}//end impl for ThreshParams
+
+/// Rounds the range lower to upper outwards to whole numbers in [0,255],
+/// so every 8-bit value inside the range stays inside.
+/// Float noise within 0.001 of a whole number isn't rounded outwards.
+fn widen_to_u8(lower: f32, upper: f32) -> (u8, u8) {
+    (
+        (lower + 0.001).floor().clamp(0., 255.) as u8,
+        (upper - 0.001).ceil().clamp(0., 255.) as u8,
+    )
+}//end widen_to_u8()
+
+/// Gets the whole numbers in [0,255] that an Outside(below, above)
+/// range leaves out, as the inclusive range (lower, upper) of them,
+/// widening what's let through the same way as widen_to_u8().
+/// Returns None if no whole numbers are left out.
+fn outside_gap_u8(below: f32, above: f32) -> Option<(u8, u8)> {
+    let (low_end, high_start) = widen_to_u8(below, above);
+    match (low_end as u16) + 1 < high_start as u16 {
+        true => Some((low_end + 1, high_start - 1)),
+        false => None,
+    }//end matching whether any values are left out
+}//end outside_gap_u8()
+
+/// The values which a depth lets through
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum DepthRange {
+    /// Values from the first bound up to the second, inclusive
+    Inside(f32, f32),
+    /// Values up to the first bound, and from the second bound up,
+    /// inclusive, such as a hue range that wraps around through 0
+    Outside(f32, f32),
+}//end enum DepthRange
+
+/// Bounds for thresholding with OpenCV's cv2.inRange()
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct OpenCvBounds {
+    pub color_space: ColorSpace,
+    /// The cv2.cvtColor() code to apply to a BGR image first, if any
+    pub conversion: Option<&'static str>,
+    /// A hue lower bound above its upper bound means the hue range
+    /// wraps around, passing from lower up to 179 and from 0 up to upper.
+    pub lower: [u8; 3],
+    pub upper: [u8; 3],
+    /// Notes on anything that couldn't be converted exactly
+    pub warnings: Vec<String>,
+}//end struct OpenCvBounds
+
+impl OpenCvBounds {
+    /// Formats the bounds as python code which builds the inRange mask
+    /// from a BGR image named img.
+    pub fn to_python(&self) -> String {
+        let mut lines = vec![format!("# Exported from Color Space Threshold Helper, color space: {:?}", self.color_space)];
+        lines.extend(self.warnings.iter().map(|w| format!("# WARNING: {w}")));
+        lines.push(match self.conversion {
+            Some(code) => format!("converted = cv2.cvtColor(img, {code})"),
+            None => "converted = img".to_string(),
+        });
+        let array = |name: &str, values: [u8; 3]| format!("{name} = np.array([{}, {}, {}], dtype=np.uint8)", values[0], values[1], values[2]);
+        lines.push(array("lower", self.lower));
+        lines.push(array("upper", self.upper));
+        match (0..3).find(|&i| self.lower[i] > self.upper[i]) {
+            None => lines.push("mask = cv2.inRange(converted, lower, upper)".to_string()),
+            Some(hue_idx) => {
+                // inRange can't wrap, so combine the ranges on either side of 0
+                let (mut high_upper, mut low_lower) = (self.upper, self.lower);
+                high_upper[hue_idx] = 179;
+                low_lower[hue_idx] = 0;
+                lines.push("# hue wraps around through 0, so pass from lower up to 179 and from 0 up to upper".to_string());
+                lines.push(array("high_upper", high_upper));
+                lines.push(array("low_lower", low_lower));
+                lines.push("mask = cv2.inRange(converted, lower, high_upper) | cv2.inRange(converted, low_lower, upper)".to_string());
+            },
+        }//end matching whether hue wraps around
+        lines.join("\n")
+    }//end to_python()
+}//end impl for OpenCvBounds
+
 #[derive(Clone,Debug,PartialEq)]
 pub struct ImageToProc {
     img: DynamicImage,
@@ -38,6 +636,31 @@ impl ImageToProc {
         )
     }//end read_image()
 
+    /// Attempts to save the image to the path given, using the
+    /// image format implied by the path's extension.
+    /// Formats without an alpha channel, like JPEG, get the alpha dropped.
+    pub fn save_image(img: &DynamicImage, path: &PathBuf) -> Result<(), ImageError> {
+        let format = ImageFormat::from_path(path)?;
+        match format {
+            ImageFormat::Jpeg => DynamicImage::ImageRgb8(img.to_rgb8()).save_with_format(path, format),
+            _ => img.save_with_format(path, format),
+        }//end matching whether we need to convert before saving
+    }//end save_image()
+
+    /// Creates a downscaled copy of the image for quick previews,
+    /// keeping the aspect ratio so neither side is over max_dim.
+    pub fn preview(&self, max_dim: u32) -> ImageToProc {
+        if self.img.width() <= max_dim && self.img.height() <= max_dim {return self.clone();}
+        ImageToProc {
+            img: self.img.thumbnail(max_dim, max_dim)
+        }//end struct construction
+    }//end preview()
+
+    /// Wraps an image that's already in memory
+    pub fn from_image(img: DynamicImage) -> ImageToProc {
+        ImageToProc { img }
+    }//end from_image()
+
     /// Gets reference to contained image object
     pub fn get_image(&self) -> &DynamicImage {&self.img}
 
@@ -52,6 +675,66 @@ impl ImageToProc {
         )
     }//end deconstruct()
 
+    /// Maps pos, picked on a copy of this image with the (width, height)
+    /// of from_size, to the pixel of ours under its middle, or None if
+    /// it's off the image.
+    pub fn scale_point(&self, pos: (u32,u32), from_size: (u32,u32)) -> Option<(u32,u32)> {
+        let (width, height) = self.img.dimensions();
+        if pos.0 >= from_size.0 || pos.1 >= from_size.1 {return None;}
+        let x = ((pos.0 as f32 + 0.5) * width as f32 / from_size.0 as f32) as u32;
+        let y = ((pos.1 as f32 + 0.5) * height as f32 / from_size.1 as f32) as u32;
+        Some((x.min(width.checked_sub(1)?), y.min(height.checked_sub(1)?)))
+    }//end scale_point()
+
+    /// Gets the rgb of the pixel at pos, or None if it's off the image
+    pub fn rgb_at(&self, pos: (u32,u32)) -> Option<[u8; 3]> {
+        if !self.img.in_bounds(pos.0, pos.1) {return None;}
+        let rgba = self.img.get_pixel(pos.0, pos.1).0;
+        Some([rgba[0], rgba[1], rgba[2]])
+    }//end rgb_at()
+
+    /// Gets the rgb of each pixel in region, which was picked on a copy of
+    /// this image with the (width, height) of from_size, so each picked
+    /// pixel may stand for several of ours. Fully transparent pixels are skipped.
+    pub fn sample_pixels(&self, region: &SampleRegion, from_size: (u32,u32)) -> Vec<[u8; 3]> {
+        let (width, height) = self.img.dimensions();
+        if width == 0 || height == 0 || from_size.0 == 0 || from_size.1 == 0 {return Vec::new();}
+        let scale_x = width as f32 / from_size.0 as f32;
+        let scale_y = height as f32 / from_size.1 as f32;
+        // a picked pixel covers from its start up to the start of the next
+        let our_x = |x: u32| ((x as f32 * scale_x) as u32).min(width);
+        let our_y = |y: u32| ((y as f32 * scale_y) as u32).min(height);
+        let (x_range, y_range, polygon) = match region {
+            SampleRegion::Point(x, y) => {
+                let Some((x, y)) = self.scale_point((*x, *y), from_size) else {return Vec::new();};
+                (x..x + 1, y..y + 1, None)
+            },
+            SampleRegion::Rect(corner1, corner2) => (
+                our_x(corner1.0.min(corner2.0))..our_x(corner1.0.max(corner2.0) + 1),
+                our_y(corner1.1.min(corner2.1))..our_y(corner1.1.max(corner2.1) + 1),
+                None,
+            ),
+            SampleRegion::Lasso(points) => {
+                if points.len() < 3 {return Vec::new();}
+                let polygon: Vec<(f32,f32)> = points.iter()
+                    .map(|(x, y)| ((*x as f32 + 0.5) * scale_x, (*y as f32 + 0.5) * scale_y))
+                    .collect();
+                let (min_x, max_x) = points.iter().fold((u32::MAX, 0), |(lo, hi), (x, _)| (lo.min(*x), hi.max(*x)));
+                let (min_y, max_y) = points.iter().fold((u32::MAX, 0), |(lo, hi), (_, y)| (lo.min(*y), hi.max(*y)));
+                (our_x(min_x)..our_x(max_x + 1), our_y(min_y)..our_y(max_y + 1), Some(polygon))
+            },
+        };
+        y_range.flat_map(|y| x_range.clone().map(move |x| (x, y)))
+            .filter(|(x, y)| match &polygon {
+                Some(polygon) => is_inside_polygon((*x as f32 + 0.5, *y as f32 + 0.5), polygon),
+                None => true,
+            })
+            .map(|(x, y)| self.img.get_pixel(x, y).0)
+            .filter(|rgba| rgba[3] > 0)
+            .map(|rgba| [rgba[0], rgba[1], rgba[2]])
+            .collect()
+    }//end sample_pixels()
+
     /// Creates a small, blank image
     pub fn blank() -> ImageToProc {
         ImageToProc {
@@ -59,13 +742,201 @@ impl ImageToProc {
         }//end struct construction
     }//end blank()
 
-    /// Thresholds the image, returning a new image that has been thresholded
-    pub fn threshold_img(&self, params: ThreshParams, thresh_color: (u8,u8,u8)) -> DynamicImage {
+    /// Converts every pixel of the image into target in its native units,
+    /// in parallel rows, giving the values in row-major order.
+    pub fn convert_native(&self, target: ColorSpace, white: WhitePoint) -> Vec<[f32; 3]> {
+        let convert = |px: &[u8]| convert_from_rgb_native([px[0],px[1],px[2]], target, white);
+        match &self.img {
+            DynamicImage::ImageRgb8(buf) => buf.as_raw().par_chunks_exact(3).map(convert).collect(),
+            DynamicImage::ImageRgba8(buf) => buf.as_raw().par_chunks_exact(4).map(convert).collect(),
+            other => other.to_rgb8().as_raw().par_chunks_exact(3).map(convert).collect(),
+        }//end matching whether we can work on the raw buffer directly
+    }//end convert_native()
+
+    /// Counts how many pixels land in each of bins equal steps of our
+    /// [0,255] scale for each channel of target, in parallel rows.
+    pub fn channel_histograms(&self, target: ColorSpace, white: WhitePoint, bins: usize) -> [Vec<u32>; 3] {
+        if bins == 0 {return Default::default();}
+        let bin_of = |value: f32| ((value / 255. * bins as f32) as usize).min(bins - 1);
+        let empty = || [vec![0u32; bins], vec![0u32; bins], vec![0u32; bins]];
+        let count = |raw: &[u8], channels: usize| raw.par_chunks_exact(channels)
+            .fold(empty, |mut counts, px| {
+                let values = convert_from_rgb_f32([px[0],px[1],px[2]], target, white);
+                for (channel_counts, value) in counts.iter_mut().zip(values) {
+                    channel_counts[bin_of(value)] += 1;
+                }//end counting each channel of pixel
+                counts
+            })
+            .reduce(empty, |mut counts, other| {
+                for (channel_counts, other_counts) in counts.iter_mut().zip(other) {
+                    channel_counts.iter_mut().zip(other_counts).for_each(|(count, other)| *count += other);
+                }//end adding up each channel
+                counts
+            });
+        match &self.img {
+            DynamicImage::ImageRgb8(buf) => count(buf.as_raw(), 3),
+            DynamicImage::ImageRgba8(buf) => count(buf.as_raw(), 4),
+            other => count(other.to_rgb8().as_raw(), 3),
+        }//end matching whether we can work on the raw buffer directly
+    }//end channel_histograms()
+
+    /// Thresholds the image, returning a new image that has been thresholded,
+    /// or the error from ThreshParams::to_expr() if params aren't valid
+    pub fn threshold_img(&self, params: ThreshParams, thresh_color: (u8,u8,u8)) -> Result<DynamicImage, String> {
+        self.threshold_img_with_progress(&params, thresh_color, OutputMode::Overlay, |_| (), || false)
+            .map(|th_img| th_img.expect("Thresholding can't be cancelled without a cancel check"))
+    }//end threshold_img()
+
+    /// Thresholds the image like threshold_img(), but with the output mode
+    /// given, calls progress with the fraction of rows finished so far,
+    /// and gives up by returning Ok(None) as soon as cancelled returns true.
+    pub fn threshold_img_with_progress(
+        &self,
+        params: &ThreshParams,
+        thresh_color: (u8,u8,u8),
+        mode: OutputMode,
+        progress: impl Fn(f32) + Sync,
+        cancelled: impl Fn() -> bool + Sync,
+    ) -> Result<Option<DynamicImage>, String> {
+        let test = PixelTest::new(params)?;
+        let color_space = params.color_space;
+        let white_point = params.white_point;
+        Ok(self.threshold_img_with_converter(params, &test, thresh_color, mode, |rgb| convert_from_rgb_f32(rgb, color_space, white_point), progress, cancelled))
+    }//end threshold_img_with_progress()
+
+    /// Thresholds the image like threshold_img_with_progress(), but looks up
+    /// each pixel's converted color in lut instead of converting it.
+    /// If lut is for a different color space or white point than params,
+    /// this falls back to converting directly.
+    pub fn threshold_img_with_lut(
+        &self,
+        params: &ThreshParams,
+        thresh_color: (u8,u8,u8),
+        mode: OutputMode,
+        lut: &ConversionLut,
+        progress: impl Fn(f32) + Sync,
+        cancelled: impl Fn() -> bool + Sync,
+    ) -> Result<Option<DynamicImage>, String> {
+        if lut.color_space() != params.color_space || lut.white_point() != params.white_point {
+            return self.threshold_img_with_progress(params, thresh_color, mode, progress, cancelled);
+        }//end if lut doesn't match the color space we want
+        let test = PixelTest::new(params)?;
+        Ok(self.threshold_img_with_converter(params, &test, thresh_color, mode, |rgb| lut.convert(rgb), progress, cancelled))
+    }//end threshold_img_with_lut()
+
+    /// Does the thresholding for the other threshold functions, with test
+    /// built from params, using convert to get each pixel in the color
+    /// space of params, and convert_from_rgb_f32() for any others.
+    /// Rgb8 and Rgba8 images are processed straight from their raw buffer,
+    /// while other color types go through Rgba8 first.
+    #[allow(clippy::too_many_arguments)]
+    fn threshold_img_with_converter(
+        &self,
+        params: &ThreshParams,
+        test: &PixelTest,
+        thresh_color: (u8,u8,u8),
+        mode: OutputMode,
+        convert: impl Fn([u8; 3]) -> [f32; 3] + Sync,
+        progress: impl Fn(f32) + Sync,
+        cancelled: impl Fn() -> bool + Sync,
+    ) -> Option<DynamicImage> {
+        let (width, height) = self.img.dimensions();
+        let white_point = params.white_point;
+        let convert = |rgb: [u8; 3], space: ColorSpace| match space == params.color_space {
+            true => convert(rgb),
+            false => convert_from_rgb_f32(rgb, space, white_point),
+        };
+        match mode {
+            OutputMode::Overlay => {
+                let paint = |_: &[u8], out_px: &mut [u8], in_thresh: bool| {
+                    if !in_thresh {
+                        out_px[0] = thresh_color.0;
+                        out_px[1] = thresh_color.1;
+                        out_px[2] = thresh_color.2;
+                        if out_px.len() == 4 {out_px[3] = 255;}
+                    }//end if we should mark pixel with thresh_color
+                };
+                match &self.img {
+                    DynamicImage::ImageRgb8(buf) => {
+                        let mut new_buf = buf.clone();
+                        threshold_rows::<3,3>(buf, &mut new_buf, width, test, &convert, &progress, &cancelled, &paint)?;
+                        Some(DynamicImage::ImageRgb8(new_buf))
+                    },
+                    DynamicImage::ImageRgba8(buf) => {
+                        let mut new_buf = buf.clone();
+                        threshold_rows::<4,4>(buf, &mut new_buf, width, test, &convert, &progress, &cancelled, &paint)?;
+                        Some(DynamicImage::ImageRgba8(new_buf))
+                    },
+                    other => {
+                        let buf = other.to_rgba8();
+                        let mut new_buf = buf.clone();
+                        threshold_rows::<4,4>(&buf, &mut new_buf, width, test, &convert, &progress, &cancelled, &paint)?;
+                        let new_img = DynamicImage::ImageRgba8(new_buf);
+                        Some(match other.color() {
+                            ColorType::L8 => DynamicImage::ImageLuma8(new_img.to_luma8()),
+                            ColorType::La8 => DynamicImage::ImageLumaA8(new_img.to_luma_alpha8()),
+                            ColorType::Rgb16 => DynamicImage::ImageRgb16(new_img.to_rgb16()),
+                            ColorType::Rgba16 => DynamicImage::ImageRgba16(new_img.to_rgba16()),
+                            ColorType::L16 => DynamicImage::ImageLuma16(new_img.to_luma16()),
+                            ColorType::La16 => DynamicImage::ImageLumaA16(new_img.to_luma_alpha16()),
+                            ColorType::Rgb32F => DynamicImage::ImageRgb32F(new_img.to_rgb32f()),
+                            ColorType::Rgba32F => DynamicImage::ImageRgba32F(new_img.to_rgba32f()),
+                            _ => new_img,
+                        })//end converting back to original color type
+                    },
+                }//end matching whether we can work on the raw buffer directly
+            },
+            OutputMode::Mask | OutputMode::InvertedMask => {
+                let pass_value = match mode {
+                    OutputMode::InvertedMask => 0,
+                    _ => 255,
+                };
+                let paint = |_: &[u8], out_px: &mut [u8], in_thresh: bool| {
+                    out_px[0] = match in_thresh {
+                        true => pass_value,
+                        false => 255 - pass_value,
+                    };
+                };
+                let mut mask = GrayImage::new(width, height);
+                match &self.img {
+                    DynamicImage::ImageRgb8(buf) => threshold_rows::<3,1>(buf, &mut mask, width, test, &convert, &progress, &cancelled, &paint)?,
+                    DynamicImage::ImageRgba8(buf) => threshold_rows::<4,1>(buf, &mut mask, width, test, &convert, &progress, &cancelled, &paint)?,
+                    other => threshold_rows::<4,1>(&other.to_rgba8(), &mut mask, width, test, &convert, &progress, &cancelled, &paint)?,
+                }//end matching whether we can work on the raw buffer directly
+                Some(DynamicImage::ImageLuma8(mask))
+            },
+            OutputMode::Transparent => {
+                let paint = |src_px: &[u8], out_px: &mut [u8], in_thresh: bool| {
+                    out_px[..3].copy_from_slice(&src_px[..3]);
+                    out_px[3] = match (in_thresh, src_px.len()) {
+                        (false, _) => 0,
+                        (true, 4) => src_px[3],
+                        (true, _) => 255,
+                    };
+                };
+                let mut new_buf = RgbaImage::new(width, height);
+                match &self.img {
+                    DynamicImage::ImageRgb8(buf) => threshold_rows::<3,4>(buf, &mut new_buf, width, test, &convert, &progress, &cancelled, &paint)?,
+                    DynamicImage::ImageRgba8(buf) => threshold_rows::<4,4>(buf, &mut new_buf, width, test, &convert, &progress, &cancelled, &paint)?,
+                    other => threshold_rows::<4,4>(&other.to_rgba8(), &mut new_buf, width, test, &convert, &progress, &cancelled, &paint)?,
+                }//end matching whether we can work on the raw buffer directly
+                Some(DynamicImage::ImageRgba8(new_buf))
+            },
+        }//end matching output mode
+    }//end threshold_img_with_converter()
+
+    /// A simple per-pixel reference implementation, which goes through
+    /// get_pixel() and put_pixel() for every pixel on one thread.
+    /// This is kept to benchmark and check the faster
+    /// threshold_img_with_progress() against.
+    pub fn threshold_img_reference(&self, params: &ThreshParams, thresh_color: (u8,u8,u8)) -> Result<DynamicImage, String> {
         let mut new_img = DynamicImage::new(self.img.width(), self.img.height(), self.img.color());
+        let test = PixelTest::new(params)?;
+        let convert = |rgb: [u8; 3], space: ColorSpace| convert_from_rgb_f32(rgb, space, params.white_point);
+        let mut converted = vec![[0f32; 3]; test.buffer_len()];
         self.img.pixels()
             .for_each(|(x,y,mut value)|{
-                let converted_pixel = convert_from_rgb([value.0[0],value.0[1],value.0[2]], params.color_space);
-                if !ImageToProc::is_pixel_in_threshold(converted_pixel, &params) {
+                if !test.passes([value.0[0],value.0[1],value.0[2]], &mut converted, &convert) {
                     value.0[0] = thresh_color.0;
                     value.0[1] = thresh_color.1;
                     value.0[2] = thresh_color.2;
@@ -75,54 +946,261 @@ impl ImageToProc {
             }
         );
 
-        return new_img;
-    }//end threshold_img()
-
-    /// Returns true if the pixel is within the threshold given, false otherwise
-    fn is_pixel_in_threshold(pixel: [u8; 3], params: &ThreshParams) -> bool {
-        let d1 = pixel[0];
-        let d2 = pixel[1];
-        let d3 = pixel[2];
-        let d1_b = match params.depth1_pass {
-            true => d1 >= params.depth1_min && d1 <= params.depth1_max,
-            false => d1 <= params.depth1_min && d1 >= params.depth1_max,
-        };
-        let d2_b = match params.depth2_pass {
-            true => d2 >= params.depth2_min && d2 <= params.depth2_max,
-            false => d2 <= params.depth2_min && d2 >= params.depth2_max,
-        };
-        let d3_b = match params.depth3_pass {
-            true => d3 >= params.depth3_min && d3 <= params.depth3_max,
-            false => d3 <= params.depth3_min && d3 >= params.depth3_max,
-        };
-        d1_b && d2_b && d3_b
-    }//end is_pixel_in_threshold
+        Ok(new_img)
+    }//end threshold_img_reference()
 }//end impl for ImageToProc
 
+/// Returns true if point is inside polygon, by counting how many
+/// of its edges a ray going right from point crosses
+fn is_inside_polygon(point: (f32,f32), polygon: &[(f32,f32)]) -> bool {
+    let mut inside = false;
+    let mut prev = polygon[polygon.len() - 1];
+    for &vertex in polygon {
+        if (vertex.1 > point.1) != (prev.1 > point.1) {
+            let cross_x = vertex.0 + (point.1 - vertex.1) * (prev.0 - vertex.0) / (prev.1 - vertex.1);
+            if point.0 < cross_x {inside = !inside;}
+        }//end if edge spans the height of point
+        prev = vertex;
+    }//end checking each edge
+    inside
+}//end is_inside_polygon()
+
+/// How pixels get tested against params, picked once per threshold
+enum PixelTest<'a> {
+    /// Just the depths, checked straight from the values in their color
+    /// space, since walking the expression tree for them is much slower
+    Depths(&'a ThreshParams),
+    /// An expression, with the color spaces it needs pixels converted into
+    Expr(ThreshExpr, Vec<ColorSpace>),
+}//end enum PixelTest
+
+impl PixelTest<'_> {
+    /// Picks the depths when params have no expression or rules,
+    /// and the expression from ThreshParams::to_expr() otherwise,
+    /// giving its error if the expression or rules aren't valid
+    fn new(params: &ThreshParams) -> Result<PixelTest<'_>, String> {
+        match params.expression.is_none() && params.rules.is_empty() {
+            true => Ok(PixelTest::Depths(params)),
+            false => {
+                let expr = params.to_expr()?;
+                let spaces = expr.spaces();
+                Ok(PixelTest::Expr(expr, spaces))
+            },
+        }//end matching whether we need the expression
+    }//end new()
+
+    /// Gets how many color spaces a pixel is converted into at once,
+    /// which is how long the buffer given to passes() should be
+    fn buffer_len(&self) -> usize {
+        match self {
+            PixelTest::Depths(_) => 0,
+            PixelTest::Expr(_, spaces) => spaces.len(),
+        }//end matching how we test pixels
+    }//end buffer_len()
+
+    /// Returns true if rgb passes, using convert to get it in each color
+    /// space needed and converted to hold the values for the expression
+    fn passes(&self, rgb: [u8; 3], converted: &mut [[f32; 3]], convert: &impl Fn([u8; 3], ColorSpace) -> [f32; 3]) -> bool {
+        match self {
+            PixelTest::Depths(params) => params.depths_pass(convert(rgb, params.color_space)),
+            PixelTest::Expr(expr, spaces) => {
+                for (values, space) in converted.iter_mut().zip(spaces) {*values = convert(rgb, *space);}
+                expr.matches(&|space| lookup_space(spaces, converted, space))
+            },
+        }//end matching how we test pixels
+    }//end passes()
+}//end impl for PixelTest
+
+/// Gets the values in the same place of converted as space is in spaces
+fn lookup_space(spaces: &[ColorSpace], converted: &[[f32; 3]], space: ColorSpace) -> [f32; 3] {
+    spaces.iter().zip(converted)
+        .find(|(s, _)| **s == space)
+        .map(|(_, values)| *values)
+        .unwrap_or_default()
+}//end lookup_space()
+
+/// Thresholds a raw buffer of CH channel pixels, splitting the rows
+/// between threads, and calls paint with each source pixel, the
+/// matching OUT_CH channel pixel in out, and whether it passes test.
+/// Within a row, the last pixel's result is reused while the color
+/// stays the same, since neighbouring pixels often match and
+/// conversion is the expensive part.
+/// Returns None if cancelled before finishing.
+#[allow(clippy::too_many_arguments)]
+fn threshold_rows<const CH: usize, const OUT_CH: usize>(
+    src: &[u8],
+    out: &mut [u8],
+    width: u32,
+    test: &PixelTest,
+    convert: &(impl Fn([u8; 3], ColorSpace) -> [f32; 3] + Sync),
+    progress: &(impl Fn(f32) + Sync),
+    cancelled: &(impl Fn() -> bool + Sync),
+    paint: &(impl Fn(&[u8], &mut [u8], bool) + Sync),
+) -> Option<()> {
+    let row_len = width as usize * CH;
+    let out_row_len = width as usize * OUT_CH;
+    if row_len == 0 {return Some(());}
+    let height = src.len() / row_len;
+    let rows_done = AtomicUsize::new(0);
+    src.par_chunks_exact(row_len)
+        .zip(out.par_chunks_exact_mut(out_row_len))
+        .try_for_each(|(src_row, out_row)| {
+            if cancelled() {return None;}
+            let mut last_rgb: Option<([u8; 3], bool)> = None;
+            let mut converted = vec![[0f32; 3]; test.buffer_len()];
+            for (src_px, out_px) in src_row.chunks_exact(CH).zip(out_row.chunks_exact_mut(OUT_CH)) {
+                let rgb = [src_px[0],src_px[1],src_px[2]];
+                let in_thresh = match last_rgb {
+                    Some((last, in_thresh)) if last == rgb => in_thresh,
+                    _ => {
+                        let in_thresh = test.passes(rgb, &mut converted, convert);
+                        last_rgb = Some((rgb, in_thresh));
+                        in_thresh
+                    },
+                };
+                paint(src_px, out_px, in_thresh);
+            }//end looping over each pixel in row
+            let done = rows_done.fetch_add(1, Ordering::Relaxed) + 1;
+            progress(done as f32 / height as f32);
+            Some(())
+        })
+}//end threshold_rows()
+
+/// A table of every 8-bit rgb color already converted into one
+/// color space, so thresholding the same image again with different
+/// slider values only pays for lookups instead of conversions.
+/// Values are kept at full precision, so thresholding with a table gives
+/// exactly what converting directly would. Each table takes 192MiB,
+/// so only the most recently used one is cached.
+#[derive(Clone,Debug,PartialEq)]
+pub struct ConversionLut {
+    color_space: ColorSpace,
+    white_point: WhitePoint,
+    table: Vec<[f32; 3]>,
+}//end struct ConversionLut
+
+/// The most recently built lookup table, shared between threshold jobs
+static LUT_CACHE: Mutex<Option<Arc<ConversionLut>>> = Mutex::new(None);
+
+impl ConversionLut {
+    /// Precomputes the table for color_space under white_point,
+    /// converting all 2^24 colors in parallel
+    pub fn new(color_space: ColorSpace, white_point: WhitePoint) -> ConversionLut {
+        ConversionLut::new_with_cancel(color_space, white_point, || false).expect("Building was never cancelled")
+    }//end new()
+
+    /// Precomputes the table like new(), but gives up and returns None
+    /// once cancelled returns true, which is checked for every red value
+    pub fn new_with_cancel(color_space: ColorSpace, white_point: WhitePoint, cancelled: impl Fn() -> bool + Sync) -> Option<ConversionLut> {
+        ConversionLut::build(color_space, white_point, |_| true, cancelled)
+    }//end new_with_cancel()
+
+    /// Precomputes only the colors with one of the red values given,
+    /// leaving the rest of the table at 0, which lets tests check the
+    /// table without converting all 2^24 colors.
+    pub fn new_for_reds(color_space: ColorSpace, white_point: WhitePoint, reds: &[u8]) -> ConversionLut {
+        ConversionLut::build(color_space, white_point, |r| reds.contains(&r), || false).expect("Building was never cancelled")
+    }//end new_for_reds()
+
+    /// Fills in the table for every red value that wanted returns true for,
+    /// or returns None once cancelled returns true
+    fn build(color_space: ColorSpace, white_point: WhitePoint, wanted: impl Fn(u8) -> bool + Sync, cancelled: impl Fn() -> bool + Sync) -> Option<ConversionLut> {
+        let mut table = vec![[0f32; 3]; 1 << 24];
+        table.par_chunks_mut(1 << 16).enumerate().try_for_each(|(r, chunk)| {
+            if cancelled() {return None;}
+            if !wanted(r as u8) {return Some(());}
+            for (gb, entry) in chunk.iter_mut().enumerate() {
+                *entry = convert_from_rgb_f32([r as u8, (gb >> 8) as u8, gb as u8], color_space, white_point);
+            }//end converting each color with this red value
+            Some(())
+        })?;
+        Some(ConversionLut { color_space, white_point, table })
+    }//end build()
+
+    /// Gets the table for color_space and white_point, reusing the last
+    /// one built if it was for the same ones and building it otherwise.
+    /// The cache isn't locked while building, and None is returned if
+    /// cancelled returns true before the table is done.
+    pub fn cached(color_space: ColorSpace, white_point: WhitePoint, cancelled: impl Fn() -> bool + Sync) -> Option<Arc<ConversionLut>> {
+        {
+            let mut cache = LUT_CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            match cache.as_ref() {
+                Some(lut) if lut.color_space == color_space && lut.white_point == white_point => return Some(lut.clone()),
+                // drop the old table before building, so we don't hold two at once
+                _ => *cache = None,
+            }//end matching whether we already have this table
+        }//end checking the cache
+        let lut = Arc::new(ConversionLut::new_with_cancel(color_space, white_point, cancelled)?);
+        let mut cache = LUT_CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *cache = Some(lut.clone());
+        Some(lut)
+    }//end cached()
+
+    pub fn color_space(&self) -> ColorSpace {self.color_space}
+
+    pub fn white_point(&self) -> WhitePoint {self.white_point}
+
+    /// Gets the same result as convert_from_rgb_f32() would for this color space and white point
+    pub fn convert(&self, rgb: [u8; 3]) -> [f32; 3] {
+        self.table[(rgb[0] as usize) << 16 | (rgb[1] as usize) << 8 | rgb[2] as usize]
+    }//end convert()
+}//end impl for ConversionLut
+
 /// Converts depth-3 rgb values into target color space
 pub fn convert_from_rgb(u8_rgb: [u8; 3], target: ColorSpace) -> [u8; 3] {
+    convert_from_rgb_with_white(u8_rgb, target, WhitePoint::D65)
+}//end convert_from_rgb()
+
+/// Converts depth-3 rgb values into target color space,
+/// with Lab, LCh, Luv and XYZ relative to the white point given
+pub fn convert_from_rgb_with_white(u8_rgb: [u8; 3], target: ColorSpace, white: WhitePoint) -> [u8; 3] {
+    let c64 = convert_to_internal(u8_rgb, target, white);
+    match target {
+        // the recommendations specify rounding to the nearest code value
+        ColorSpace::YCbCr601 | ColorSpace::YCbCr601Studio |
+        ColorSpace::YCbCr709 | ColorSpace::YCbCr709Studio |
+        ColorSpace::YCbCr2020 | ColorSpace::YCbCr2020Studio => c64.map(|c| c.round() as u8),
+        _ => c64.map(|c| c.ceil() as u8),
+    }//end matching how target is quantized
+}//end convert_from_rgb_with_white()
+
+/// Converts depth-3 rgb values into target color space without
+/// rounding, keeping each channel on the same [0,255] scale as
+/// convert_from_rgb_with_white() but with its full precision.
+pub fn convert_from_rgb_f32(u8_rgb: [u8; 3], target: ColorSpace, white: WhitePoint) -> [f32; 3] {
+    convert_to_internal(u8_rgb, target, white).map(|c| c as f32)
+}//end convert_from_rgb_f32()
+
+/// Converts depth-3 rgb values into target color space in its native
+/// units, such as degrees of hue or Lab L, a and b, as listed by
+/// ColorSpace::native_ranges()
+pub fn convert_from_rgb_native(u8_rgb: [u8; 3], target: ColorSpace, white: WhitePoint) -> [f32; 3] {
+    let internal = convert_from_rgb_f32(u8_rgb, target, white);
+    [0, 1, 2].map(|i| target.to_native(i, internal[i], white))
+}//end convert_from_rgb_native()
+
+/// Does the conversion for the convert_from_rgb functions,
+/// giving each channel scaled to [0,255] but not rounded.
+fn convert_to_internal(u8_rgb: [u8; 3], target: ColorSpace, white: WhitePoint) -> [f64; 3] {
     let r = u8_rgb[0];
     let g = u8_rgb[1];
     let b = u8_rgb[2];
-    let big_rgb: Srgb<f64> = Srgb::new(r as f64, g as f64, b as f64);
-    let c8: [u8; 3];
+    // palette expects each channel in [0,1]
+    let big_rgb: Srgb<f64> = Srgb::new(r as f64 / 255., g as f64 / 255., b as f64 / 255.);
+    let c64: [f64; 3];
     match target {
-        ColorSpace::RGB => c8 = [r,g,b],
+        ColorSpace::RGB => c64 = [r as f64, g as f64, b as f64],
         ColorSpace::HSBorHSV => {
             let hsv = Hsv::from_color(big_rgb);
             // get values in variables
             let mut h = hsv.get_hue().into_positive_degrees();
             let mut s = hsv.saturation;
             // issues with palette conversion, so we do it ourself
-            let v = r.max(g).max(b);
+            let v = r.max(g).max(b) as f64;
             // Convert all values to scale of [0,255]
             h = (h * 255.) / 360.;
             s = (s * 255.) / 1.;
-            // Convert all values to u8
-            let h = h.ceil() as u8;
-            let s = s.ceil() as u8;
-            // c64 = [hsv.h,hsv.s,hsv.v];
-            c8 = [h,s,v];
+            c64 = [h,s,v];
         },
         ColorSpace::HSL => {
             let hsl = Hsl::from_color(big_rgb);
@@ -134,11 +1212,7 @@ pub fn convert_from_rgb(u8_rgb: [u8; 3], target: ColorSpace) -> [u8; 3] {
             h = (h * 255.) / 360.;
             s = (s * 255.) / 1.;
             l = (l * 255.) / 1.;
-            // Convert all values to u8
-            let h = h.ceil() as u8;
-            let s = s.ceil() as u8;
-            let l = l.ceil() as u8;
-            c8 = [h,s,l];
+            c64 = [h,s,l];
         },
         ColorSpace::HSI => {
             // conversion formula from:
@@ -146,52 +1220,34 @@ pub fn convert_from_rgb(u8_rgb: [u8; 3], target: ColorSpace) -> [u8; 3] {
             let r = r as f64;
             let g = g as f64;
             let b = b as f64;
-            let r = r / (r + g + b);
-            let g = g / (r + g + b);
-            let b = b / (r + g + b);
-            let h;
-            if b <= g {
-                h = (
-                    0.5 * ((r - g) + (r - b))
-                    /
-                    ( (r - g).powi(2) + (r - b) * (g - b) ).powf(0.5)
-                ).acos();
-            } else {
-                h = 2. * f64::consts::PI - (
-                    0.5 * ( (r - g) + (r - b) )
-                    /
-                    ( (r - g).powi(2) + (r - b) * (g - b) ).powf(0.5)
-                ).acos();
-            }
+            let sum = r + g + b;
+            let i = sum / 3.;
+            // normalize so that r + g + b = 1
+            let (r, g, b) = match sum > 0. {
+                true => (r / sum, g / sum, b / sum),
+                false => (1. / 3., 1. / 3., 1. / 3.),
+            };
             let s = 1. - 3. * r.min(g).min(b);
-            let i = (r + g + b) / (3. * 255.);
+            let denom = ( (r - g).powi(2) + (r - b) * (g - b) ).powf(0.5);
+            // hue is undefined for grays, so leave it at 0
+            let h = match denom > 0. {
+                false => 0.,
+                true => {
+                    let theta = (0.5 * ((r - g) + (r - b)) / denom).clamp(-1., 1.).acos();
+                    if b <= g {theta} else {2. * f64::consts::PI - theta}
+                },
+            };
             // convert values into ranges of [0,360], [0,100], [0,255]
             let h = h * 180. / f64::consts::PI;
             let s = s * 100.;
-            let i = i * 255.;
             // normalize values into ranges of [0,255],[0,255],[0,255]
             let h = (h * 255.) / 360.;
             let s = (s * 255.) / 100.;
-            let i = i;
-            c8 = [h as u8,s as u8,i as u8];
+            c64 = [h,s,i];
         },
         ColorSpace::LabCIE => {
-            let lab = Lab::from_color(big_rgb);
-            // get values in variables
-            let mut l = lab.l;
-            let mut a = lab.a;
-            let mut b = lab.b;
-            // Convert all values to scale of [0,255]
-            l = (l * 255.) / 100.;
-            a += 128.;
-            a = a.min(255.).max(0.);
-            b += 128.;
-            b = b.min(255.).max(0.);
-            // convert all values to u8
-            let l = l.ceil() as u8;
-            let a = a.ceil() as u8;
-            let b = b.ceil() as u8;
-            c8 = [l,a,b];
+            let lab = lab_from_xyz(xyz_under_white(big_rgb, white), white);
+            c64 = [scale_to_internal(lab[0], 0., 100.), scale_to_internal(lab[1], -128., 128.), scale_to_internal(lab[2], -128., 128.)];
         },
         ColorSpace::YUV => {
             // conversion formula taken from:
@@ -203,13 +1259,156 @@ pub fn convert_from_rgb(u8_rgb: [u8; 3], target: ColorSpace) -> [u8; 3] {
             let y = r * 0.299000 + g * 0.587000 + b * 0.114000;
             let u = r * -0.168736 + g * -0.331264 + b * 0.5 + 128.;
             let v = r * 0.500000 + g * -0.418688 + b * -0.081312 + 128.;
-            // convert to u8
-            let y = y.ceil() as u8;
-            let u = u.ceil() as u8;
-            let v = v.ceil() as u8;
-            c8 = [y,u,v];
+            c64 = [y,u,v].map(|c| c.clamp(0., 255.));
+        },
+        ColorSpace::CMY => c64 = [255 - r, 255 - g, 255 - b].map(|c| c as f64),
+        ColorSpace::HunterLab => {
+            // conversion formula from:
+            // https://support.hunterlab.com/hc/en-us/articles/203997095-Hunter-Lab-Color-Scale-an08-96a2
+            let xyz = Xyz::from_color(big_rgb);
+            let (xn, yn, zn) = (0.95047, 1., 1.08883);
+            let ka = (175. / 198.04) * (xn + yn) * 100.;
+            let kb = (70. / 218.11) * (yn + zn) * 100.;
+            let y_ratio = xyz.y / yn;
+            let l = 100. * y_ratio.sqrt();
+            let (a, b) = match y_ratio > 0. {
+                true => (
+                    ka * (xyz.x / xn - y_ratio) / y_ratio.sqrt(),
+                    kb * (y_ratio - xyz.z / zn) / y_ratio.sqrt(),
+                ),
+                false => (0., 0.),
+            };
+            c64 = [scale_to_internal(l, 0., 100.), scale_to_internal(a, -70., 110.), scale_to_internal(b, -200., 60.)];
+        },
+        ColorSpace::LCH => {
+            let [l, a, b] = lab_from_xyz(xyz_under_white(big_rgb, white), white);
+            let chroma = a.hypot(b);
+            let h = b.atan2(a).to_degrees().rem_euclid(360.);
+            c64 = [scale_to_internal(l, 0., 100.), scale_to_internal(chroma, 0., 134.), scale_to_internal(h, 0., 360.)];
+        },
+        ColorSpace::LUV => {
+            let [l, u, v] = luv_from_xyz(xyz_under_white(big_rgb, white), white);
+            c64 = [scale_to_internal(l, 0., 100.), scale_to_internal(u, -84., 176.), scale_to_internal(v, -135., 108.)];
+        },
+        ColorSpace::XYZ => {
+            let [x, y, z] = xyz_under_white(big_rgb, white);
+            let [xn, yn, zn] = white.xyz();
+            c64 = [scale_to_internal(x, 0., xn), scale_to_internal(y, 0., yn), scale_to_internal(z, 0., zn)];
+        },
+        ColorSpace::YXY => {
+            let yxy = Yxy::from_color(big_rgb);
+            c64 = [scale_to_internal(yxy.luma, 0., 1.), scale_to_internal(yxy.x, 0., 1.), scale_to_internal(yxy.y, 0., 1.)];
+        },
+        ColorSpace::YCbCr601 => c64 = rgb_to_ycbcr(u8_rgb, 0.299, 0.114, false),
+        ColorSpace::YCbCr601Studio => c64 = rgb_to_ycbcr(u8_rgb, 0.299, 0.114, true),
+        ColorSpace::YCbCr709 => c64 = rgb_to_ycbcr(u8_rgb, 0.2126, 0.0722, false),
+        ColorSpace::YCbCr709Studio => c64 = rgb_to_ycbcr(u8_rgb, 0.2126, 0.0722, true),
+        ColorSpace::YCbCr2020 => c64 = rgb_to_ycbcr(u8_rgb, 0.2627, 0.0593, false),
+        ColorSpace::YCbCr2020Studio => c64 = rgb_to_ycbcr(u8_rgb, 0.2627, 0.0593, true),
+        ColorSpace::YIQ => {
+            // FCC NTSC coefficients, from:
+            // https://en.wikipedia.org/wiki/YIQ
+            let (r, g, b) = (big_rgb.red, big_rgb.green, big_rgb.blue);
+            let y = r * 0.299 + g * 0.587 + b * 0.114;
+            let i = r * 0.5959 + g * -0.2746 + b * -0.3213;
+            let q = r * 0.2115 + g * -0.5227 + b * 0.3112;
+            c64 = [scale_to_internal(y, 0., 1.), scale_to_internal(i, -0.5959, 0.5959), scale_to_internal(q, -0.5227, 0.5227)];
+        },
+        ColorSpace::OKLab => {
+            let oklab = Oklab::from_color(big_rgb);
+            c64 = [scale_to_internal(oklab.l, 0., 1.), scale_to_internal(oklab.a, -0.24, 0.28), scale_to_internal(oklab.b, -0.32, 0.2)];
+        },
+        ColorSpace::OKLCh => {
+            let oklch = Oklch::from_color(big_rgb);
+            let h = oklch.get_hue().into_positive_degrees();
+            c64 = [scale_to_internal(oklch.l, 0., 1.), scale_to_internal(oklch.chroma, 0., 0.33), scale_to_internal(h, 0., 360.)];
         },
     };//end matching based on target color space
 
-    return c8;
-}//end convert_from_rgb()
\ No newline at end of file
+    c64
+}//end convert_to_internal()
+
+/// Bradford cone response matrix, used for chromatic adaptation
+const BRADFORD: [[f64; 3]; 3] = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+/// Inverse of BRADFORD
+const BRADFORD_INV: [[f64; 3]; 3] = [
+    [0.9869929, -0.1470543, 0.1599627],
+    [0.4323053, 0.5183603, 0.0492912],
+    [-0.0085287, 0.0400428, 0.9684867],
+];
+
+fn mul_matrix(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}//end mul_matrix()
+
+/// Gets the XYZ of an sRGB color as seen under white, using the
+/// Bradford transform to adapt from sRGB's own D65 white.
+/// The result is scaled so that sRGB white lands on white.xyz().
+fn xyz_under_white(rgb: Srgb<f64>, white: WhitePoint) -> [f64; 3] {
+    let xyz = Xyz::from_color(rgb);
+    let xyz = [xyz.x, xyz.y, xyz.z];
+    if white == WhitePoint::D65 {return xyz;}
+    let src_cone = mul_matrix(&BRADFORD, WhitePoint::D65.xyz());
+    let dst_cone = mul_matrix(&BRADFORD, white.xyz());
+    let cone = mul_matrix(&BRADFORD, xyz);
+    let adapted = [0, 1, 2].map(|i| cone[i] * dst_cone[i] / src_cone[i]);
+    mul_matrix(&BRADFORD_INV, adapted)
+}//end xyz_under_white()
+
+/// Gets CIE L*a*b* from XYZ relative to white
+fn lab_from_xyz(xyz: [f64; 3], white: WhitePoint) -> [f64; 3] {
+    // conversion formula from:
+    // http://www.brucelindbloom.com/index.html?Eqn_XYZ_to_Lab.html
+    let f = |t: f64| match t > 216. / 24389. {
+        true => t.cbrt(),
+        false => (24389. / 27. * t + 16.) / 116.,
+    };
+    let [xn, yn, zn] = white.xyz();
+    let (fx, fy, fz) = (f(xyz[0] / xn), f(xyz[1] / yn), f(xyz[2] / zn));
+    [116. * fy - 16., 500. * (fx - fy), 200. * (fy - fz)]
+}//end lab_from_xyz()
+
+/// Gets CIE L*u*v* from XYZ relative to white
+fn luv_from_xyz(xyz: [f64; 3], white: WhitePoint) -> [f64; 3] {
+    // conversion formula from:
+    // http://www.brucelindbloom.com/index.html?Eqn_XYZ_to_Luv.html
+    let [xn, yn, zn] = white.xyz();
+    let y_ratio = xyz[1] / yn;
+    let l = match y_ratio > 216. / 24389. {
+        true => 116. * y_ratio.cbrt() - 16.,
+        false => 24389. / 27. * y_ratio,
+    };
+    let denom = xyz[0] + 15. * xyz[1] + 3. * xyz[2];
+    if denom <= 0. {return [l, 0., 0.];}
+    let white_denom = xn + 15. * yn + 3. * zn;
+    let u = 13. * l * (4. * xyz[0] / denom - 4. * xn / white_denom);
+    let v = 13. * l * (9. * xyz[1] / denom - 9. * yn / white_denom);
+    [l, u, v]
+}//end luv_from_xyz()
+
+/// Converts gamma-encoded RGB to YCbCr using the luma coefficients kr and kb
+/// of one of the ITU-R recommendations (BT.601, BT.709 or BT.2020).
+/// Full range uses all of [0,255] with chroma centered on 128, as in JPEG,
+/// while studio range puts Y in [16,235] and Cb and Cr in [16,240].
+fn rgb_to_ycbcr(u8_rgb: [u8; 3], kr: f64, kb: f64, studio_range: bool) -> [f64; 3] {
+    let r = u8_rgb[0] as f64 / 255.;
+    let g = u8_rgb[1] as f64 / 255.;
+    let b = u8_rgb[2] as f64 / 255.;
+    let y = kr * r + (1. - kr - kb) * g + kb * b;
+    let cb = (b - y) / (2. * (1. - kb));
+    let cr = (r - y) / (2. * (1. - kr));
+    let (y, cb, cr) = match studio_range {
+        true => (16. + 219. * y, 128. + 224. * cb, 128. + 224. * cr),
+        false => (255. * y, 128. + 255. * cb, 128. + 255. * cr),
+    };
+    [y, cb, cr].map(|c| c.clamp(0., 255.))
+}//end rgb_to_ycbcr()
+
+/// Linearly maps value from [min,max] onto [0,255], clamping anything outside
+fn scale_to_internal(value: f64, min: f64, max: f64) -> f64 {
+    ((value - min) * 255. / (max - min)).clamp(0., 255.)
+}//end scale_to_internal()
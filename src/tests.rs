@@ -1,4 +1,6 @@
-use crate::{enums::ColorSpace, process};
+use image::{DynamicImage, RgbaImage};
+
+use crate::{enums::{ColorSpace, OutputMode, SamplePadding, SampleRegion, WhitePoint}, expr::ThreshExpr, preset::ThreshPreset, process::{self, ConversionLut, ImageToProc, PixelCheck, ThreshParams, ThreshRule}};
 
 const ALLOWED_DIFF: u8 = 1;
 
@@ -116,7 +118,7 @@ pub fn hsl_conversion_yellow() {
 pub fn hsl_conversion_green() {
     let rgb = [0,153,51];
     let hsl = process::convert_from_rgb(rgb, ColorSpace::HSL);
-    let dif = [hsl[0].abs_diff(197),hsl[1].abs_diff(255),hsl[2].abs_diff(76)];
+    let dif = [hsl[0].abs_diff(99),hsl[1].abs_diff(255),hsl[2].abs_diff(76)];
     assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] hsl green", dif[0]);
     assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] hsl green", dif[1]);
     assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] hsl green", dif[2]);
@@ -156,7 +158,7 @@ pub fn hsi_conversion_red() {
 pub fn hsi_conversion_blue() {
     let rgb = [102,0,204];
     let hsi = process::convert_from_rgb(rgb, ColorSpace::HSI);
-    let dif = [hsi[0].abs_diff(197),hsi[1].abs_diff(255),hsi[2].abs_diff(102)];
+    let dif = [hsi[0].abs_diff(191),hsi[1].abs_diff(255),hsi[2].abs_diff(102)];
     assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] hsi blue", dif[0]);
     assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] hsi blue", dif[1]);
     assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] hsi blue", dif[2]);
@@ -300,4 +302,1566 @@ pub fn yuv_conversion_green() {
     assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yuv green", dif[0]);
     assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yuv green", dif[1]);
     assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yuv green", dif[2]);
-}
\ No newline at end of file
+}
+
+#[test]
+pub fn cmy_conversion_white() {
+    let rgb = [255,255,255];
+    let cmy = process::convert_from_rgb(rgb, ColorSpace::CMY);
+    let dif = [cmy[0].abs_diff(0),cmy[1].abs_diff(0),cmy[2].abs_diff(0)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] cmy white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] cmy white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] cmy white", dif[2]);
+}
+
+#[test]
+pub fn cmy_conversion_black() {
+    let rgb = [0,0,0];
+    let cmy = process::convert_from_rgb(rgb, ColorSpace::CMY);
+    let dif = [cmy[0].abs_diff(255),cmy[1].abs_diff(255),cmy[2].abs_diff(255)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] cmy black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] cmy black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] cmy black", dif[2]);
+}
+
+#[test]
+pub fn cmy_conversion_red() {
+    let rgb = [255,80,80];
+    let cmy = process::convert_from_rgb(rgb, ColorSpace::CMY);
+    let dif = [cmy[0].abs_diff(0),cmy[1].abs_diff(175),cmy[2].abs_diff(175)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] cmy red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] cmy red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] cmy red", dif[2]);
+}
+
+#[test]
+pub fn cmy_conversion_blue() {
+    let rgb = [102,0,204];
+    let cmy = process::convert_from_rgb(rgb, ColorSpace::CMY);
+    let dif = [cmy[0].abs_diff(153),cmy[1].abs_diff(255),cmy[2].abs_diff(51)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] cmy blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] cmy blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] cmy blue", dif[2]);
+}
+
+#[test]
+pub fn cmy_conversion_yellow() {
+    let rgb = [255,255,204];
+    let cmy = process::convert_from_rgb(rgb, ColorSpace::CMY);
+    let dif = [cmy[0].abs_diff(0),cmy[1].abs_diff(0),cmy[2].abs_diff(51)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] cmy yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] cmy yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] cmy yellow", dif[2]);
+}
+
+#[test]
+pub fn cmy_conversion_green() {
+    let rgb = [0,153,51];
+    let cmy = process::convert_from_rgb(rgb, ColorSpace::CMY);
+    let dif = [cmy[0].abs_diff(255),cmy[1].abs_diff(102),cmy[2].abs_diff(204)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] cmy green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] cmy green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] cmy green", dif[2]);
+}
+
+#[test]
+pub fn xyz_conversion_white() {
+    let rgb = [255,255,255];
+    let xyz = process::convert_from_rgb(rgb, ColorSpace::XYZ);
+    let dif = [xyz[0].abs_diff(255),xyz[1].abs_diff(255),xyz[2].abs_diff(255)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] xyz white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] xyz white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] xyz white", dif[2]);
+}
+
+#[test]
+pub fn xyz_conversion_black() {
+    let rgb = [0,0,0];
+    let xyz = process::convert_from_rgb(rgb, ColorSpace::XYZ);
+    let dif = [xyz[0].abs_diff(0),xyz[1].abs_diff(0),xyz[2].abs_diff(0)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] xyz black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] xyz black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] xyz black", dif[2]);
+}
+
+#[test]
+pub fn xyz_conversion_red() {
+    let rgb = [255,80,80];
+    let xyz = process::convert_from_rgb(rgb, ColorSpace::XYZ);
+    let dif = [xyz[0].abs_diff(123),xyz[1].abs_diff(71),xyz[2].abs_diff(25)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] xyz red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] xyz red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] xyz red", dif[2]);
+}
+
+#[test]
+pub fn xyz_conversion_blue() {
+    let rgb = [102,0,204];
+    let xyz = process::convert_from_rgb(rgb, ColorSpace::XYZ);
+    let dif = [xyz[0].abs_diff(44),xyz[1].abs_diff(19),xyz[2].abs_diff(135)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] xyz blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] xyz blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] xyz blue", dif[2]);
+}
+
+#[test]
+pub fn xyz_conversion_yellow() {
+    let rgb = [255,255,204];
+    let xyz = process::convert_from_rgb(rgb, ColorSpace::XYZ);
+    let dif = [xyz[0].abs_diff(236),xyz[1].abs_diff(248),xyz[2].abs_diff(167)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] xyz yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] xyz yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] xyz yellow", dif[2]);
+}
+
+#[test]
+pub fn xyz_conversion_green() {
+    let rgb = [0,153,51];
+    let xyz = process::convert_from_rgb(rgb, ColorSpace::XYZ);
+    let dif = [xyz[0].abs_diff(33),xyz[1].abs_diff(59),xyz[2].abs_diff(17)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] xyz green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] xyz green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] xyz green", dif[2]);
+}
+
+#[test]
+pub fn yxy_conversion_white() {
+    let rgb = [255,255,255];
+    let yxy = process::convert_from_rgb(rgb, ColorSpace::YXY);
+    let dif = [yxy[0].abs_diff(255),yxy[1].abs_diff(80),yxy[2].abs_diff(84)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yxy white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yxy white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yxy white", dif[2]);
+}
+
+#[test]
+pub fn yxy_conversion_black() {
+    let rgb = [0,0,0];
+    let yxy = process::convert_from_rgb(rgb, ColorSpace::YXY);
+    let dif = [yxy[0].abs_diff(0),yxy[1].abs_diff(0),yxy[2].abs_diff(0)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yxy black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yxy black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yxy black", dif[2]);
+}
+
+#[test]
+pub fn yxy_conversion_red() {
+    let rgb = [255,80,80];
+    let yxy = process::convert_from_rgb(rgb, ColorSpace::YXY);
+    let dif = [yxy[0].abs_diff(71),yxy[1].abs_diff(139),yxy[2].abs_diff(85)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yxy red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yxy red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yxy red", dif[2]);
+}
+
+#[test]
+pub fn yxy_conversion_blue() {
+    let rgb = [102,0,204];
+    let yxy = process::convert_from_rgb(rgb, ColorSpace::YXY);
+    let dif = [yxy[0].abs_diff(19),yxy[1].abs_diff(52),yxy[2].abs_diff(23)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yxy blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yxy blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yxy blue", dif[2]);
+}
+
+#[test]
+pub fn yxy_conversion_yellow() {
+    let rgb = [255,255,204];
+    let yxy = process::convert_from_rgb(rgb, ColorSpace::YXY);
+    let dif = [yxy[0].abs_diff(248),yxy[1].abs_diff(88),yxy[2].abs_diff(97)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yxy yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yxy yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yxy yellow", dif[2]);
+}
+
+#[test]
+pub fn yxy_conversion_green() {
+    let rgb = [0,153,51];
+    let yxy = process::convert_from_rgb(rgb, ColorSpace::YXY);
+    let dif = [yxy[0].abs_diff(59),yxy[1].abs_diff(73),yxy[2].abs_diff(140)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yxy green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yxy green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yxy green", dif[2]);
+}
+
+#[test]
+pub fn lch_conversion_white() {
+    let rgb = [255,255,255];
+    let lch = process::convert_from_rgb(rgb, ColorSpace::LCH);
+    // hue is undefined without chroma, so only lightness and chroma are checked
+    let dif = [lch[0].abs_diff(255),lch[1].abs_diff(0)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] lch white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] lch white", dif[1]);
+}
+
+#[test]
+pub fn lch_conversion_black() {
+    let rgb = [0,0,0];
+    let lch = process::convert_from_rgb(rgb, ColorSpace::LCH);
+    // hue is undefined without chroma, so only lightness and chroma are checked
+    let dif = [lch[0].abs_diff(0),lch[1].abs_diff(0)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] lch black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] lch black", dif[1]);
+}
+
+#[test]
+pub fn lch_conversion_red() {
+    let rgb = [255,80,80];
+    let lch = process::convert_from_rgb(rgb, ColorSpace::LCH);
+    let dif = [lch[0].abs_diff(152),lch[1].abs_diff(146),lch[2].abs_diff(22)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] lch red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] lch red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] lch red", dif[2]);
+}
+
+#[test]
+pub fn lch_conversion_blue() {
+    let rgb = [102,0,204];
+    let lch = process::convert_from_rgb(rgb, ColorSpace::LCH);
+    let dif = [lch[0].abs_diff(83),lch[1].abs_diff(201),lch[2].abs_diff(221)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] lch blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] lch blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] lch blue", dif[2]);
+}
+
+#[test]
+pub fn lch_conversion_yellow() {
+    let rgb = [255,255,204];
+    let lch = process::convert_from_rgb(rgb, ColorSpace::LCH);
+    let dif = [lch[0].abs_diff(253),lch[1].abs_diff(49),lch[2].abs_diff(77)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] lch yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] lch yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] lch yellow", dif[2]);
+}
+
+#[test]
+pub fn lch_conversion_green() {
+    let rgb = [0,153,51];
+    let lch = process::convert_from_rgb(rgb, ColorSpace::LCH);
+    let dif = [lch[0].abs_diff(141),lch[1].abs_diff(134),lch[2].abs_diff(101)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] lch green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] lch green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] lch green", dif[2]);
+}
+
+#[test]
+pub fn luv_conversion_white() {
+    let rgb = [255,255,255];
+    let luv = process::convert_from_rgb(rgb, ColorSpace::LUV);
+    let dif = [luv[0].abs_diff(255),luv[1].abs_diff(83),luv[2].abs_diff(142)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] luv white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] luv white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] luv white", dif[2]);
+}
+
+#[test]
+pub fn luv_conversion_black() {
+    let rgb = [0,0,0];
+    let luv = process::convert_from_rgb(rgb, ColorSpace::LUV);
+    let dif = [luv[0].abs_diff(0),luv[1].abs_diff(83),luv[2].abs_diff(142)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] luv black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] luv black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] luv black", dif[2]);
+}
+
+#[test]
+pub fn luv_conversion_red() {
+    let rgb = [255,80,80];
+    let luv = process::convert_from_rgb(rgb, ColorSpace::LUV);
+    let dif = [luv[0].abs_diff(152),luv[1].abs_diff(214),luv[2].abs_diff(173)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] luv red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] luv red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] luv red", dif[2]);
+}
+
+#[test]
+pub fn luv_conversion_blue() {
+    let rgb = [102,0,204];
+    let luv = process::convert_from_rgb(rgb, ColorSpace::LUV);
+    let dif = [luv[0].abs_diff(83),luv[1].abs_diff(92),luv[2].abs_diff(32)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] luv blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] luv blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] luv blue", dif[2]);
+}
+
+#[test]
+pub fn luv_conversion_yellow() {
+    let rgb = [255,255,204];
+    let luv = process::convert_from_rgb(rgb, ColorSpace::LUV);
+    let dif = [luv[0].abs_diff(253),luv[1].abs_diff(86),luv[2].abs_diff(181)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] luv yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] luv yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] luv yellow", dif[2]);
+}
+
+#[test]
+pub fn luv_conversion_green() {
+    let rgb = [0,153,51];
+    let luv = process::convert_from_rgb(rgb, ColorSpace::LUV);
+    let dif = [luv[0].abs_diff(141),luv[1].abs_diff(33),luv[2].abs_diff(202)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] luv green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] luv green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] luv green", dif[2]);
+}
+
+#[test]
+pub fn hunterlab_conversion_white() {
+    let rgb = [255,255,255];
+    let hunterlab = process::convert_from_rgb(rgb, ColorSpace::HunterLab);
+    let dif = [hunterlab[0].abs_diff(255),hunterlab[1].abs_diff(100),hunterlab[2].abs_diff(197)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] hunterlab white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] hunterlab white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] hunterlab white", dif[2]);
+}
+
+#[test]
+pub fn hunterlab_conversion_black() {
+    let rgb = [0,0,0];
+    let hunterlab = process::convert_from_rgb(rgb, ColorSpace::HunterLab);
+    let dif = [hunterlab[0].abs_diff(0),hunterlab[1].abs_diff(100),hunterlab[2].abs_diff(197)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] hunterlab black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] hunterlab black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] hunterlab black", dif[2]);
+}
+
+#[test]
+pub fn hunterlab_conversion_red() {
+    let rgb = [255,80,80];
+    let hunterlab = process::convert_from_rgb(rgb, ColorSpace::HunterLab);
+    let dif = [hunterlab[0].abs_diff(134),hunterlab[1].abs_diff(194),hunterlab[2].abs_diff(219)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] hunterlab red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] hunterlab red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] hunterlab red", dif[2]);
+}
+
+#[test]
+pub fn hunterlab_conversion_blue() {
+    let rgb = [102,0,204];
+    let hunterlab = process::convert_from_rgb(rgb, ColorSpace::HunterLab);
+    let dif = [hunterlab[0].abs_diff(69),hunterlab[1].abs_diff(191),hunterlab[2].abs_diff(84)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] hunterlab blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] hunterlab blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] hunterlab blue", dif[2]);
+}
+
+#[test]
+pub fn hunterlab_conversion_yellow() {
+    let rgb = [255,255,204];
+    let hunterlab = process::convert_from_rgb(rgb, ColorSpace::HunterLab);
+    let dif = [hunterlab[0].abs_diff(252),hunterlab[1].abs_diff(88),hunterlab[2].abs_diff(218)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] hunterlab yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] hunterlab yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] hunterlab yellow", dif[2]);
+}
+
+#[test]
+pub fn hunterlab_conversion_green() {
+    let rgb = [0,153,51];
+    let hunterlab = process::convert_from_rgb(rgb, ColorSpace::HunterLab);
+    let dif = [hunterlab[0].abs_diff(123),hunterlab[1].abs_diff(47),hunterlab[2].abs_diff(219)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] hunterlab green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] hunterlab green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] hunterlab green", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_conversion_white() {
+    let rgb = [255,255,255];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601);
+    let dif = [ycbcr601[0].abs_diff(255),ycbcr601[1].abs_diff(128),ycbcr601[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 white", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_conversion_black() {
+    let rgb = [0,0,0];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601);
+    let dif = [ycbcr601[0].abs_diff(0),ycbcr601[1].abs_diff(128),ycbcr601[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 black", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_conversion_red() {
+    let rgb = [255,80,80];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601);
+    let dif = [ycbcr601[0].abs_diff(132),ycbcr601[1].abs_diff(98),ycbcr601[2].abs_diff(216)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 red", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_conversion_blue() {
+    let rgb = [102,0,204];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601);
+    let dif = [ycbcr601[0].abs_diff(54),ycbcr601[1].abs_diff(213),ycbcr601[2].abs_diff(162)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 blue", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_conversion_yellow() {
+    let rgb = [255,255,204];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601);
+    let dif = [ycbcr601[0].abs_diff(249),ycbcr601[1].abs_diff(102),ycbcr601[2].abs_diff(132)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 yellow", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_conversion_green() {
+    let rgb = [0,153,51];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601);
+    let dif = [ycbcr601[0].abs_diff(96),ycbcr601[1].abs_diff(103),ycbcr601[2].abs_diff(60)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 green", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_studio_conversion_white() {
+    let rgb = [255,255,255];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601Studio);
+    let dif = [ycbcr601[0].abs_diff(235),ycbcr601[1].abs_diff(128),ycbcr601[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 studio white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 studio white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 studio white", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_studio_conversion_black() {
+    let rgb = [0,0,0];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601Studio);
+    let dif = [ycbcr601[0].abs_diff(16),ycbcr601[1].abs_diff(128),ycbcr601[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 studio black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 studio black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 studio black", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_studio_conversion_red() {
+    let rgb = [255,80,80];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601Studio);
+    let dif = [ycbcr601[0].abs_diff(130),ycbcr601[1].abs_diff(102),ycbcr601[2].abs_diff(205)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 studio red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 studio red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 studio red", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_studio_conversion_blue() {
+    let rgb = [102,0,204];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601Studio);
+    let dif = [ycbcr601[0].abs_diff(62),ycbcr601[1].abs_diff(202),ycbcr601[2].abs_diff(158)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 studio blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 studio blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 studio blue", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_studio_conversion_yellow() {
+    let rgb = [255,255,204];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601Studio);
+    let dif = [ycbcr601[0].abs_diff(230),ycbcr601[1].abs_diff(106),ycbcr601[2].abs_diff(132)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 studio yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 studio yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 studio yellow", dif[2]);
+}
+
+#[test]
+pub fn ycbcr601_studio_conversion_green() {
+    let rgb = [0,153,51];
+    let ycbcr601 = process::convert_from_rgb(rgb, ColorSpace::YCbCr601Studio);
+    let dif = [ycbcr601[0].abs_diff(98),ycbcr601[1].abs_diff(106),ycbcr601[2].abs_diff(68)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr601 studio green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr601 studio green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr601 studio green", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_conversion_white() {
+    let rgb = [255,255,255];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709);
+    let dif = [ycbcr709[0].abs_diff(255),ycbcr709[1].abs_diff(128),ycbcr709[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 white", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_conversion_black() {
+    let rgb = [0,0,0];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709);
+    let dif = [ycbcr709[0].abs_diff(0),ycbcr709[1].abs_diff(128),ycbcr709[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 black", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_conversion_red() {
+    let rgb = [255,80,80];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709);
+    let dif = [ycbcr709[0].abs_diff(117),ycbcr709[1].abs_diff(108),ycbcr709[2].abs_diff(216)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 red", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_conversion_blue() {
+    let rgb = [102,0,204];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709);
+    let dif = [ycbcr709[0].abs_diff(36),ycbcr709[1].abs_diff(218),ycbcr709[2].abs_diff(170)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 blue", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_conversion_yellow() {
+    let rgb = [255,255,204];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709);
+    let dif = [ycbcr709[0].abs_diff(251),ycbcr709[1].abs_diff(102),ycbcr709[2].abs_diff(130)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 yellow", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_conversion_green() {
+    let rgb = [0,153,51];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709);
+    let dif = [ycbcr709[0].abs_diff(113),ycbcr709[1].abs_diff(95),ycbcr709[2].abs_diff(56)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 green", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_studio_conversion_white() {
+    let rgb = [255,255,255];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709Studio);
+    let dif = [ycbcr709[0].abs_diff(235),ycbcr709[1].abs_diff(128),ycbcr709[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 studio white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 studio white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 studio white", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_studio_conversion_black() {
+    let rgb = [0,0,0];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709Studio);
+    let dif = [ycbcr709[0].abs_diff(16),ycbcr709[1].abs_diff(128),ycbcr709[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 studio black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 studio black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 studio black", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_studio_conversion_red() {
+    let rgb = [255,80,80];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709Studio);
+    let dif = [ycbcr709[0].abs_diff(117),ycbcr709[1].abs_diff(110),ycbcr709[2].abs_diff(205)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 studio red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 studio red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 studio red", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_studio_conversion_blue() {
+    let rgb = [102,0,204];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709Studio);
+    let dif = [ycbcr709[0].abs_diff(47),ycbcr709[1].abs_diff(207),ycbcr709[2].abs_diff(165)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 studio blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 studio blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 studio blue", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_studio_conversion_yellow() {
+    let rgb = [255,255,204];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709Studio);
+    let dif = [ycbcr709[0].abs_diff(232),ycbcr709[1].abs_diff(106),ycbcr709[2].abs_diff(130)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 studio yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 studio yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 studio yellow", dif[2]);
+}
+
+#[test]
+pub fn ycbcr709_studio_conversion_green() {
+    let rgb = [0,153,51];
+    let ycbcr709 = process::convert_from_rgb(rgb, ColorSpace::YCbCr709Studio);
+    let dif = [ycbcr709[0].abs_diff(113),ycbcr709[1].abs_diff(99),ycbcr709[2].abs_diff(65)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr709 studio green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr709 studio green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr709 studio green", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_conversion_white() {
+    let rgb = [255,255,255];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020);
+    let dif = [ycbcr2020[0].abs_diff(255),ycbcr2020[1].abs_diff(128),ycbcr2020[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 white", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_conversion_black() {
+    let rgb = [0,0,0];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020);
+    let dif = [ycbcr2020[0].abs_diff(0),ycbcr2020[1].abs_diff(128),ycbcr2020[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 black", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_conversion_red() {
+    let rgb = [255,80,80];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020);
+    let dif = [ycbcr2020[0].abs_diff(126),ycbcr2020[1].abs_diff(104),ycbcr2020[2].abs_diff(216)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 red", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_conversion_blue() {
+    let rgb = [102,0,204];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020);
+    let dif = [ycbcr2020[0].abs_diff(39),ycbcr2020[1].abs_diff(216),ycbcr2020[2].abs_diff(171)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 blue", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_conversion_yellow() {
+    let rgb = [255,255,204];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020);
+    let dif = [ycbcr2020[0].abs_diff(252),ycbcr2020[1].abs_diff(102),ycbcr2020[2].abs_diff(130)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 yellow", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_conversion_green() {
+    let rgb = [0,153,51];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020);
+    let dif = [ycbcr2020[0].abs_diff(107),ycbcr2020[1].abs_diff(98),ycbcr2020[2].abs_diff(56)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 green", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_studio_conversion_white() {
+    let rgb = [255,255,255];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020Studio);
+    let dif = [ycbcr2020[0].abs_diff(235),ycbcr2020[1].abs_diff(128),ycbcr2020[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 studio white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 studio white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 studio white", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_studio_conversion_black() {
+    let rgb = [0,0,0];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020Studio);
+    let dif = [ycbcr2020[0].abs_diff(16),ycbcr2020[1].abs_diff(128),ycbcr2020[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 studio black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 studio black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 studio black", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_studio_conversion_red() {
+    let rgb = [255,80,80];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020Studio);
+    let dif = [ycbcr2020[0].abs_diff(124),ycbcr2020[1].abs_diff(107),ycbcr2020[2].abs_diff(205)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 studio red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 studio red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 studio red", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_studio_conversion_blue() {
+    let rgb = [102,0,204];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020Studio);
+    let dif = [ycbcr2020[0].abs_diff(49),ycbcr2020[1].abs_diff(205),ycbcr2020[2].abs_diff(166)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 studio blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 studio blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 studio blue", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_studio_conversion_yellow() {
+    let rgb = [255,255,204];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020Studio);
+    let dif = [ycbcr2020[0].abs_diff(232),ycbcr2020[1].abs_diff(106),ycbcr2020[2].abs_diff(130)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 studio yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 studio yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 studio yellow", dif[2]);
+}
+
+#[test]
+pub fn ycbcr2020_studio_conversion_green() {
+    let rgb = [0,153,51];
+    let ycbcr2020 = process::convert_from_rgb(rgb, ColorSpace::YCbCr2020Studio);
+    let dif = [ycbcr2020[0].abs_diff(108),ycbcr2020[1].abs_diff(102),ycbcr2020[2].abs_diff(64)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] ycbcr2020 studio green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] ycbcr2020 studio green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] ycbcr2020 studio green", dif[2]);
+}
+
+#[test]
+pub fn yiq_conversion_white() {
+    let rgb = [255,255,255];
+    let yiq = process::convert_from_rgb(rgb, ColorSpace::YIQ);
+    let dif = [yiq[0].abs_diff(255),yiq[1].abs_diff(128),yiq[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yiq white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yiq white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yiq white", dif[2]);
+}
+
+#[test]
+pub fn yiq_conversion_black() {
+    let rgb = [0,0,0];
+    let yiq = process::convert_from_rgb(rgb, ColorSpace::YIQ);
+    let dif = [yiq[0].abs_diff(0),yiq[1].abs_diff(128),yiq[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yiq black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yiq black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yiq black", dif[2]);
+}
+
+#[test]
+pub fn yiq_conversion_red() {
+    let rgb = [255,80,80];
+    let yiq = process::convert_from_rgb(rgb, ColorSpace::YIQ);
+    let dif = [yiq[0].abs_diff(133),yiq[1].abs_diff(215),yiq[2].abs_diff(163)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yiq red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yiq red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yiq red", dif[2]);
+}
+
+#[test]
+pub fn yiq_conversion_blue() {
+    let rgb = [102,0,204];
+    let yiq = process::convert_from_rgb(rgb, ColorSpace::YIQ);
+    let dif = [yiq[0].abs_diff(54),yiq[1].abs_diff(124),yiq[2].abs_diff(209)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yiq blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yiq blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yiq blue", dif[2]);
+}
+
+#[test]
+pub fn yiq_conversion_yellow() {
+    let rgb = [255,255,204];
+    let yiq = process::convert_from_rgb(rgb, ColorSpace::YIQ);
+    let dif = [yiq[0].abs_diff(250),yiq[1].abs_diff(142),yiq[2].abs_diff(113)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yiq yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yiq yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yiq yellow", dif[2]);
+}
+
+#[test]
+pub fn yiq_conversion_green() {
+    let rgb = [0,153,51];
+    let yiq = process::convert_from_rgb(rgb, ColorSpace::YIQ);
+    let dif = [yiq[0].abs_diff(96),yiq[1].abs_diff(79),yiq[2].abs_diff(67)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] yiq green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] yiq green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] yiq green", dif[2]);
+}
+
+#[test]
+pub fn oklab_conversion_white() {
+    let rgb = [255,255,255];
+    let oklab = process::convert_from_rgb(rgb, ColorSpace::OKLab);
+    let dif = [oklab[0].abs_diff(255),oklab[1].abs_diff(118),oklab[2].abs_diff(157)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklab white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklab white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklab white", dif[2]);
+}
+
+#[test]
+pub fn oklab_conversion_black() {
+    let rgb = [0,0,0];
+    let oklab = process::convert_from_rgb(rgb, ColorSpace::OKLab);
+    let dif = [oklab[0].abs_diff(0),oklab[1].abs_diff(118),oklab[2].abs_diff(157)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklab black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklab black", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklab black", dif[2]);
+}
+
+#[test]
+pub fn oklab_conversion_red() {
+    let rgb = [255,80,80];
+    let oklab = process::convert_from_rgb(rgb, ColorSpace::OKLab);
+    let dif = [oklab[0].abs_diff(173),oklab[1].abs_diff(212),oklab[2].abs_diff(201)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklab red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklab red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklab red", dif[2]);
+}
+
+#[test]
+pub fn oklab_conversion_blue() {
+    let rgb = [102,0,204];
+    let oklab = process::convert_from_rgb(rgb, ColorSpace::OKLab);
+    let dif = [oklab[0].abs_diff(115),oklab[1].abs_diff(168),oklab[2].abs_diff(47)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklab blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklab blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklab blue", dif[2]);
+}
+
+#[test]
+pub fn oklab_conversion_yellow() {
+    let rgb = [255,255,204];
+    let oklab = process::convert_from_rgb(rgb, ColorSpace::OKLab);
+    let dif = [oklab[0].abs_diff(252),oklab[1].abs_diff(109),oklab[2].abs_diff(188)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklab yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklab yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklab yellow", dif[2]);
+}
+
+#[test]
+pub fn oklab_conversion_green() {
+    let rgb = [0,153,51];
+    let oklab = process::convert_from_rgb(rgb, ColorSpace::OKLab);
+    let dif = [oklab[0].abs_diff(152),oklab[1].abs_diff(45),oklab[2].abs_diff(206)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklab green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklab green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklab green", dif[2]);
+}
+
+#[test]
+pub fn oklab_conversion_pure_red() {
+    let rgb = [255,0,0];
+    let oklab = process::convert_from_rgb(rgb, ColorSpace::OKLab);
+    let dif = [oklab[0].abs_diff(161),oklab[1].abs_diff(228),oklab[2].abs_diff(219)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklab pure red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklab pure red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklab pure red", dif[2]);
+}
+
+#[test]
+pub fn oklab_conversion_pure_green() {
+    let rgb = [0,255,0];
+    let oklab = process::convert_from_rgb(rgb, ColorSpace::OKLab);
+    let dif = [oklab[0].abs_diff(221),oklab[1].abs_diff(3),oklab[2].abs_diff(245)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklab pure green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklab pure green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklab pure green", dif[2]);
+}
+
+#[test]
+pub fn oklab_conversion_pure_blue() {
+    let rgb = [0,0,255];
+    let oklab = process::convert_from_rgb(rgb, ColorSpace::OKLab);
+    let dif = [oklab[0].abs_diff(116),oklab[1].abs_diff(102),oklab[2].abs_diff(5)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklab pure blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklab pure blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklab pure blue", dif[2]);
+}
+
+#[test]
+pub fn oklch_conversion_white() {
+    let rgb = [255,255,255];
+    let oklch = process::convert_from_rgb(rgb, ColorSpace::OKLCh);
+    // hue is undefined without chroma, so only lightness and chroma are checked
+    let dif = [oklch[0].abs_diff(255),oklch[1].abs_diff(1)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklch white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklch white", dif[1]);
+}
+
+#[test]
+pub fn oklch_conversion_black() {
+    let rgb = [0,0,0];
+    let oklch = process::convert_from_rgb(rgb, ColorSpace::OKLCh);
+    // hue is undefined without chroma, so only lightness and chroma are checked
+    let dif = [oklch[0].abs_diff(0),oklch[1].abs_diff(0)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklch black", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklch black", dif[1]);
+}
+
+#[test]
+pub fn oklch_conversion_red() {
+    let rgb = [255,80,80];
+    let oklch = process::convert_from_rgb(rgb, ColorSpace::OKLCh);
+    let dif = [oklch[0].abs_diff(173),oklch[1].abs_diff(164),oklch[2].abs_diff(18)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklch red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklch red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklch red", dif[2]);
+}
+
+#[test]
+pub fn oklch_conversion_blue() {
+    let rgb = [102,0,204];
+    let oklch = process::convert_from_rgb(rgb, ColorSpace::OKLCh);
+    let dif = [oklch[0].abs_diff(115),oklch[1].abs_diff(192),oklch[2].abs_diff(209)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklch blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklch blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklch blue", dif[2]);
+}
+
+#[test]
+pub fn oklch_conversion_yellow() {
+    let rgb = [255,255,204];
+    let oklch = process::convert_from_rgb(rgb, ColorSpace::OKLCh);
+    let dif = [oklch[0].abs_diff(252),oklch[1].abs_diff(51),oklch[2].abs_diff(77)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklch yellow", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklch yellow", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklch yellow", dif[2]);
+}
+
+#[test]
+pub fn oklch_conversion_green() {
+    let rgb = [0,153,51];
+    let oklch = process::convert_from_rgb(rgb, ColorSpace::OKLCh);
+    let dif = [oklch[0].abs_diff(152),oklch[1].abs_diff(139),oklch[2].abs_diff(104)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklch green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklch green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklch green", dif[2]);
+}
+
+#[test]
+pub fn oklch_conversion_pure_red() {
+    let rgb = [255,0,0];
+    let oklch = process::convert_from_rgb(rgb, ColorSpace::OKLCh);
+    let dif = [oklch[0].abs_diff(161),oklch[1].abs_diff(200),oklch[2].abs_diff(21)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklch pure red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklch pure red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklch pure red", dif[2]);
+}
+
+#[test]
+pub fn oklch_conversion_pure_green() {
+    let rgb = [0,255,0];
+    let oklch = process::convert_from_rgb(rgb, ColorSpace::OKLCh);
+    let dif = [oklch[0].abs_diff(221),oklch[1].abs_diff(228),oklch[2].abs_diff(101)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklch pure green", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklch pure green", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklch pure green", dif[2]);
+}
+
+#[test]
+pub fn oklch_conversion_pure_blue() {
+    let rgb = [0,0,255];
+    let oklch = process::convert_from_rgb(rgb, ColorSpace::OKLCh);
+    let dif = [oklch[0].abs_diff(116),oklch[1].abs_diff(243),oklch[2].abs_diff(188)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] oklch pure blue", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] oklch pure blue", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] oklch pure blue", dif[2]);
+}
+
+#[test]
+pub fn lab_d50_conversion_white() {
+    let rgb = [255,255,255];
+    let lab = process::convert_from_rgb_with_white(rgb, ColorSpace::LabCIE, WhitePoint::D50);
+    let dif = [lab[0].abs_diff(255),lab[1].abs_diff(128),lab[2].abs_diff(128)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] lab d50 white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] lab d50 white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] lab d50 white", dif[2]);
+}
+
+#[test]
+pub fn lab_d50_conversion_pure_red() {
+    // {54.29,80.81,69.89} from Bruce Lindbloom's calculator with Bradford adaptation
+    let rgb = [255,0,0];
+    let lab = process::convert_from_rgb_with_white(rgb, ColorSpace::LabCIE, WhitePoint::D50);
+    let dif = [lab[0].abs_diff(139),lab[1].abs_diff(209),lab[2].abs_diff(198)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] lab d50 pure red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] lab d50 pure red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] lab d50 pure red", dif[2]);
+}
+
+#[test]
+pub fn lab_illuminant_a_conversion_red() {
+    let rgb = [255,80,80];
+    let lab = process::convert_from_rgb_with_white(rgb, ColorSpace::LabCIE, WhitePoint::A);
+    let dif = [lab[0].abs_diff(161),lab[1].abs_diff(189),lab[2].abs_diff(175)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] lab illuminant a red", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] lab illuminant a red", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] lab illuminant a red", dif[2]);
+}
+
+#[test]
+pub fn xyz_illuminant_a_conversion_white() {
+    let rgb = [255,255,255];
+    let xyz = process::convert_from_rgb_with_white(rgb, ColorSpace::XYZ, WhitePoint::A);
+    let dif = [xyz[0].abs_diff(255),xyz[1].abs_diff(255),xyz[2].abs_diff(255)];
+    assert!(dif[0] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[0] xyz illuminant a white", dif[0]);
+    assert!(dif[1] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[1] xyz illuminant a white", dif[1]);
+    assert!(dif[2] <= ALLOWED_DIFF, "Diff was {}, higher than allowed for diff[2] xyz illuminant a white", dif[2]);
+}
+
+#[test]
+pub fn custom_white_point_matches_standard() {
+    let custom = WhitePoint::from_str("0.96422, 1, 0.82521").expect("Couldn't parse custom white point");
+    for rgb in [[255,80,80],[102,0,204],[0,153,51]] {
+        for color_space in [ColorSpace::LabCIE, ColorSpace::LCH, ColorSpace::LUV, ColorSpace::XYZ] {
+            assert_eq!(
+                process::convert_from_rgb_with_white(rgb, color_space, custom),
+                process::convert_from_rgb_with_white(rgb, color_space, WhitePoint::D50),
+                "Custom D50 differs from D50 for {:?} in {:?}", rgb, color_space
+            );
+        }//end checking each color space that uses the white point
+    }//end checking each color
+}
+
+#[test]
+pub fn preset_toml_round_trip() {
+    let preset = ThreshPreset::new(ThreshParams {
+        extra_intervals: [vec![(230.,255.,true)], vec![], vec![(10.,20.,true),(60.5,70.,false)]],
+        ..ThreshParams::from_depths(ColorSpace::HSBorHSV, WhitePoint::Custom { x: 0.9642, y: 1., z: 0.8252 }, [(10.,40.,true), (100.,255.,true), (200.,50.,false)])
+    }, (0,255,0));
+    let toml = preset.to_toml().expect("Couldn't format preset as toml");
+    let parsed = ThreshPreset::from_toml(&toml).expect("Couldn't parse preset from toml");
+    assert_eq!(preset, parsed, "Preset changed after round trip through toml:\n{toml}");
+}
+
+#[test]
+pub fn imagej_macro_hsb() {
+    let params = ThreshParams::from_depths(ColorSpace::HSBorHSV, WhitePoint::D65, [(10.,40.,true), (0.,255.,true), (200.,50.,false)]);
+    let ij_macro = params.to_imagej_macro().expect("Couldn't export HSB as ImageJ macro");
+    assert!(ij_macro.contains("run(\"HSB Stack\");"), "Missing HSB conversion in:\n{ij_macro}");
+    assert!(ij_macro.contains("min[0]=10;\nmax[0]=40;\nfilter[0]=\"pass\";"), "Wrong depth 1 in:\n{ij_macro}");
+    assert!(ij_macro.contains("min[2]=50;\nmax[2]=200;\nfilter[2]=\"pass\";"), "Wrong depth 3 in:\n{ij_macro}");
+}
+
+#[test]
+pub fn imagej_macro_lab_shift() {
+    let params = ThreshParams::from_native_depths(ColorSpace::LabCIE, WhitePoint::D65, [
+        (0.,100.,true),(-20.,30.5,true),(-128.,127.,true),
+    ]);
+    let ij_macro = params.to_imagej_macro().expect("Couldn't export Lab as ImageJ macro");
+    for line in ["min[0]=0;","max[0]=255;","min[1]=108;","max[1]=159;","min[2]=0;","max[2]=255;"] {
+        assert!(ij_macro.contains(line), "Expected {line} in ImageJ macro:\n{ij_macro}");
+    }//end checking each bound
+}
+
+#[test]
+pub fn imagej_macro_unsupported_space() {
+    let params = ThreshParams::from_depths(ColorSpace::HSL, WhitePoint::D65, [(0.,255.,true); 3]);
+    assert!(params.to_imagej_macro().is_err(), "HSL shouldn't export to ImageJ");
+}
+
+#[test]
+pub fn opencv_bounds_hsv() {
+    let params = ThreshParams::from_depths(ColorSpace::HSBorHSV, WhitePoint::D65, [(0.,255.,true), (100.,200.,true), (10.,50.,false)]);
+    let bounds = params.to_opencv_bounds().expect("Couldn't export HSV as OpenCV bounds");
+    assert_eq!(bounds.lower, [0,100,0], "Wrong lower bounds for opencv hsv");
+    assert_eq!(bounds.upper, [179,200,255], "Wrong upper bounds for opencv hsv");
+    assert_eq!(bounds.warnings.len(), 1, "Expected a warning for the inexpressible stop channel");
+}
+
+#[test]
+pub fn opencv_bounds_rgb_order() {
+    let params = ThreshParams::from_depths(ColorSpace::RGB, WhitePoint::D65, [(1.,2.,true), (3.,4.,true), (6.,5.,false)]);
+    let bounds = params.to_opencv_bounds().expect("Couldn't export RGB as OpenCV bounds");
+    assert_eq!(bounds.lower, [5,3,1], "Wrong lower bounds for opencv bgr");
+    assert_eq!(bounds.upper, [6,4,2], "Wrong upper bounds for opencv bgr");
+    assert!(bounds.warnings.is_empty(), "Didn't expect warnings for opencv bgr");
+}
+
+#[test]
+pub fn threshold_engine_matches_reference() {
+    let rgba = RgbaImage::from_fn(64, 48, |x, y| image::Rgba([(x * 4) as u8, (y * 5) as u8, ((x + y) * 2) as u8, (x * y % 256) as u8]));
+    let params = ThreshParams::from_depths(ColorSpace::HSBorHSV, WhitePoint::D65, [(20.,180.,true), (30.,255.,true), (200.,40.,false)]);
+    let rgba = DynamicImage::ImageRgba8(rgba);
+    for img in [rgba.clone(), DynamicImage::ImageRgb8(rgba.to_rgb8()), DynamicImage::ImageLuma8(rgba.to_luma8()), DynamicImage::ImageRgb16(rgba.to_rgb16())] {
+        let img = ImageToProc::from_image(img);
+        let expected = img.threshold_img_reference(&params, (10,200,30)).expect("Couldn't threshold with the reference");
+        let actual = img.threshold_img(params.clone(), (10,200,30)).expect("Couldn't threshold");
+        assert!(expected == actual, "Threshold engine output differs for {:?}", img.get_image().color());
+    }//end checking each color type
+}
+
+#[test]
+pub fn lut_matches_conversion() {
+    // the bench checks every color, which is too slow for a debug build
+    let reds = [0, 80, 102, 153, 255];
+    let lut = ConversionLut::new_for_reds(ColorSpace::YUV, WhitePoint::D65, &reds);
+    for r in reds {
+        for g in (0..=255).step_by(15) {
+            for b in (0..=255).step_by(17) {
+                let rgb = [r, g, b];
+                assert_eq!(lut.convert(rgb), process::convert_from_rgb_f32(rgb, ColorSpace::YUV, WhitePoint::D65), "Lookup table differs for {:?}", rgb);
+            }//end checking each blue value
+        }//end checking each green value
+    }//end checking each red value
+
+    let img = ImageToProc::from_image(DynamicImage::ImageRgba8(
+        RgbaImage::from_fn(32, 32, |x, y| image::Rgba([reds[x as usize % reds.len()], (y * 8) as u8, (x * 8) as u8, 255]))
+    ));
+    let params = ThreshParams::from_depths(ColorSpace::YUV, WhitePoint::D65, [(50.,200.,true), (0.,255.,true), (0.,140.,true)]);
+    let expected = img.threshold_img(params.clone(), (0,0,0)).expect("Couldn't threshold");
+    let actual = img.threshold_img_with_lut(&params, (0,0,0), OutputMode::Overlay, &lut, |_| (), || false).expect("Couldn't threshold with lookup table");
+    assert!(Some(expected) == actual, "Thresholding with lookup table differs from converting directly");
+}
+
+#[test]
+pub fn lut_build_can_be_cancelled() {
+    assert!(ConversionLut::new_with_cancel(ColorSpace::LabCIE, WhitePoint::D65, || true).is_none(), "Cancelled lookup table was still built");
+}
+
+#[test]
+pub fn threshold_mask_output() {
+    let img = ImageToProc::from_image(DynamicImage::ImageRgb8(
+        image::RgbImage::from_fn(4, 1, |x, _| image::Rgb([(x * 80) as u8, 0, 0]))
+    ));
+    let params = ThreshParams::from_depths(ColorSpace::RGB, WhitePoint::D65, [(100.,255.,true), (0.,255.,true), (0.,255.,true)]);
+    let mask = img.threshold_img_with_progress(&params, (0,0,0), OutputMode::Mask, |_| (), || false)
+        .expect("Couldn't threshold").expect("Mask thresholding shouldn't be cancelled");
+    assert_eq!(mask.as_luma8().map(|m| m.as_raw().clone()), Some(vec![0,0,255,255]), "Wrong mask output");
+    let inverted = img.threshold_img_with_progress(&params, (0,0,0), OutputMode::InvertedMask, |_| (), || false)
+        .expect("Couldn't threshold").expect("Inverted mask thresholding shouldn't be cancelled");
+    assert_eq!(inverted.as_luma8().map(|m| m.as_raw().clone()), Some(vec![255,255,0,0]), "Wrong inverted mask output");
+}
+
+#[test]
+pub fn threshold_transparent_output() {
+    let img = ImageToProc::from_image(DynamicImage::ImageLuma8(
+        image::GrayImage::from_fn(4, 1, |x, _| image::Luma([(x * 80) as u8]))
+    ));
+    let params = ThreshParams::from_depths(ColorSpace::RGB, WhitePoint::D65, [(100.,255.,true), (0.,255.,true), (0.,255.,true)]);
+    let th_img = img.threshold_img_with_progress(&params, (0,0,0), OutputMode::Transparent, |_| (), || false)
+        .expect("Couldn't threshold").expect("Transparent thresholding shouldn't be cancelled");
+    let th_img = th_img.as_rgba8().expect("Transparent output should always be rgba8");
+    let alphas: Vec<u8> = th_img.pixels().map(|p| p.0[3]).collect();
+    assert_eq!(alphas, vec![0,0,255,255], "Wrong alpha in transparent output");
+    assert_eq!(th_img.get_pixel(3, 0).0, [240,240,240,255], "Passing pixel color changed in transparent output");
+}
+
+#[test]
+pub fn preset_without_white_point_is_d65() {
+    let toml = "[params]\ncolor_space = \"LabCIE\"\n\
+        depth1_min = 0\ndepth1_max = 255\ndepth1_pass = true\n\
+        depth2_min = 0\ndepth2_max = 255\ndepth2_pass = true\n\
+        depth3_min = 0\ndepth3_max = 255\ndepth3_pass = true\n";
+    let preset = ThreshPreset::from_toml(toml).expect("Couldn't parse preset from before white points");
+    assert_eq!(preset.params.white_point, WhitePoint::D65);
+}
+
+#[test]
+pub fn native_conversion_units() {
+    let hsb = process::convert_from_rgb_native([102,0,204], ColorSpace::HSBorHSV, WhitePoint::D65);
+    assert!((hsb[0] - 270.).abs() < 0.01, "Expected hue of 270 degrees but got {}", hsb[0]);
+    assert!((hsb[1] - 100.).abs() < 0.01, "Expected saturation of 100 percent but got {}", hsb[1]);
+    assert!((hsb[2] - 80.).abs() < 0.01, "Expected value of 80 percent but got {}", hsb[2]);
+    let lab = process::convert_from_rgb_native([255,80,80], ColorSpace::LabCIE, WhitePoint::D65);
+    for (got, expected) in lab.into_iter().zip([59.50, 65.84, 38.43]) {
+        assert!((got - expected).abs() < 0.05, "Expected lab {:?} close to {{59.50,65.84,38.43}}", lab);
+    }//end checking each lab channel
+}
+
+#[test]
+pub fn native_depths_round_trip() {
+    let native = [(20.5,40.25,true),(10.,90.,true),(75.,25.,false)];
+    let params = ThreshParams::from_native_depths(ColorSpace::HSL, WhitePoint::D65, native);
+    assert!((params.depth1_min - 20.5 * 255. / 360.).abs() < 0.001, "Wrong internal hue min {}", params.depth1_min);
+    for (got, expected) in params.native_depths().into_iter().zip(native) {
+        assert!((got.0 - expected.0).abs() < 0.001 && (got.1 - expected.1).abs() < 0.001 && got.2 == expected.2,
+            "Native depth {:?} changed to {:?} after round trip", expected, got);
+    }//end checking each depth
+}
+
+#[test]
+pub fn threshold_uses_fractional_bounds() {
+    // hue of 140 degrees is 99.17 on our scale, which rounding up to 100 used to lose
+    let img = ImageToProc::from_image(DynamicImage::ImageRgb8(image::RgbImage::from_pixel(1, 1, image::Rgb([0,153,51]))));
+    let params = ThreshParams::from_native_depths(ColorSpace::HSBorHSV, WhitePoint::D65, [
+        (139.5,140.5,true),(0.,100.,true),(0.,100.,true),
+    ]);
+    let mask = img.threshold_img_with_progress(&params, (255,0,0), OutputMode::Mask, |_| (), || false).expect("Couldn't threshold").expect("Thresholding was cancelled");
+    assert_eq!(mask.to_luma8().get_pixel(0, 0).0, [255], "Hue of 140 degrees should pass 139.5 to 140.5");
+    let params = ThreshParams::from_native_depths(ColorSpace::HSBorHSV, WhitePoint::D65, [
+        (140.2,141.,true),(0.,100.,true),(0.,100.,true),
+    ]);
+    let mask = img.threshold_img_with_progress(&params, (255,0,0), OutputMode::Mask, |_| (), || false).expect("Couldn't threshold").expect("Thresholding was cancelled");
+    assert_eq!(mask.to_luma8().get_pixel(0, 0).0, [0], "Hue of 140 degrees shouldn't pass 140.2 to 141");
+}
+
+#[test]
+pub fn convert_native_matches_per_pixel() {
+    let img = ImageToProc::from_image(DynamicImage::ImageRgba8(
+        RgbaImage::from_fn(16, 8, |x, y| image::Rgba([(x * 16) as u8, (y * 32) as u8, 200, 255]))
+    ));
+    let native = img.convert_native(ColorSpace::OKLCh, WhitePoint::D65);
+    assert_eq!(native.len(), 16 * 8);
+    for (i, value) in native.into_iter().enumerate() {
+        let (x, y) = (i % 16, i / 16);
+        let expected = process::convert_from_rgb_native([(x * 16) as u8, (y * 32) as u8, 200], ColorSpace::OKLCh, WhitePoint::D65);
+        assert_eq!(value, expected, "Native conversion differs at ({x},{y})");
+    }//end checking each pixel
+}
+
+#[test]
+pub fn opencv_bounds_lab_shift() {
+    let params = ThreshParams::from_native_depths(ColorSpace::LabCIE, WhitePoint::D65, [
+        (0.,100.,true),(-20.,30.5,true),(-128.,128.,true),
+    ]);
+    let bounds = params.to_opencv_bounds().expect("Couldn't export Lab as OpenCV bounds");
+    assert_eq!(bounds.lower, [0,108,0], "Wrong lower bounds for opencv lab");
+    assert_eq!(bounds.upper, [255,159,255], "Wrong upper bounds for opencv lab");
+}
+
+#[test]
+pub fn channel_units_match_native_ranges() {
+    for space_name in ColorSpace::get_variants() {
+        let space = ColorSpace::from_str(&space_name).expect("Every variant should parse");
+        let ranges = space.native_ranges(WhitePoint::D65);
+        for (unit, (min, max)) in space.channel_units().into_iter().zip(ranges) {
+            match unit {
+                "°" => assert_eq!((min, max), (0., 360.), "{space_name} degrees should span a full turn"),
+                "%" => assert_eq!((min, max), (0., 100.), "{space_name} percents should span 0 to 100"),
+                _ => assert_eq!(unit, "", "{space_name} has unexpected unit {unit}"),
+            }//end matching unit to its expected range
+        }//end checking each channel
+    }//end checking each color space
+}
+
+#[test]
+pub fn hue_wraps_around_through_zero() {
+    // red is at 0 degrees, magenta at 300, and green at 120
+    let img = ImageToProc::from_image(DynamicImage::ImageRgb8(image::RgbImage::from_fn(3, 1, |x, _| match x {
+        0 => image::Rgb([255,0,0]),
+        1 => image::Rgb([255,0,255]),
+        _ => image::Rgb([0,255,0]),
+    })));
+    for space in [ColorSpace::HSBorHSV, ColorSpace::HSL, ColorSpace::HSI] {
+        for (pass, expected) in [(true, [255,255,0]), (false, [0,0,255])] {
+            let params = ThreshParams::from_native_depths(space, WhitePoint::D65, [
+                (240.,20.,pass),(0.,100.,true),(0.,100.,true),
+            ]);
+            let mask = img.threshold_img_with_progress(&params, (255,0,0), OutputMode::Mask, |_| (), || false).expect("Couldn't threshold").expect("Thresholding was cancelled");
+            let got: Vec<u8> = mask.to_luma8().pixels().map(|p| p.0[0]).collect();
+            assert_eq!(got, expected, "{:?} hue from 240 to 20 with pass {pass} should give mask {:?}", space, expected);
+        }//end checking pass and stop
+    }//end checking each hue-based space
+}
+
+#[test]
+pub fn hue_stop_without_wrap_lets_through_outside() {
+    assert!(ThreshParams::depth_passes(10., 20., 40., false, true), "Hue stop should let through values below min");
+    assert!(ThreshParams::depth_passes(50., 20., 40., false, true), "Hue stop should let through values above max");
+    assert!(!ThreshParams::depth_passes(30., 20., 40., false, true), "Hue stop should stop values between min and max");
+    assert!(!ThreshParams::depth_passes(10., 20., 40., false, false), "Non-hue stop with min below max should let nothing through");
+}
+
+#[test]
+pub fn imagej_macro_hue_wrap() {
+    let params = ThreshParams::from_depths(ColorSpace::HSBorHSV, WhitePoint::D65, [(200.,20.,true), (0.,255.,true), (0.,255.,true)]);
+    let ij_macro = params.to_imagej_macro().expect("Couldn't export wrapped hue as ImageJ macro");
+    assert!(ij_macro.contains("min[0]=21;\nmax[0]=199;\nfilter[0]=\"stop\";"), "Wrapped hue should stop the gap in:\n{ij_macro}");
+    let params = ThreshParams { depth1_min: 20., depth1_max: 200., depth1_pass: false, ..params };
+    let ij_macro = params.to_imagej_macro().expect("Couldn't export hue stop as ImageJ macro");
+    assert!(ij_macro.contains("min[0]=21;\nmax[0]=199;\nfilter[0]=\"stop\";"), "Hue stop should stop inside its bounds in:\n{ij_macro}");
+}
+
+#[test]
+pub fn opencv_bounds_hue_wrap() {
+    let params = ThreshParams::from_depths(ColorSpace::HSBorHSV, WhitePoint::D65, [(200.,20.,true), (100.,200.,true), (0.,255.,true)]);
+    let bounds = params.to_opencv_bounds().expect("Couldn't export wrapped hue as OpenCV bounds");
+    assert_eq!(bounds.lower, [141,100,0], "Wrong lower bounds for wrapped opencv hue");
+    assert_eq!(bounds.upper, [15,200,255], "Wrong upper bounds for wrapped opencv hue");
+    assert!(bounds.warnings.is_empty(), "Didn't expect warnings for wrapped opencv hue");
+    let python = bounds.to_python();
+    assert!(python.contains("cv2.inRange(converted, lower, high_upper) | cv2.inRange(converted, low_lower, upper)"), "Wrapped hue should combine two masks in:\n{python}");
+}
+
+#[test]
+pub fn extra_intervals_are_ored() {
+    // lightness bands of 10 to 20 and 60 to 70 percent
+    let img = ImageToProc::from_image(DynamicImage::ImageRgb8(image::RgbImage::from_fn(4, 1, |x, _| {
+        let v = [38, 64, 166, 230][x as usize];
+        image::Rgb([v,v,v])
+    })));
+    let params = ThreshParams::from_native_intervals(ColorSpace::HSL, WhitePoint::D65, [
+        vec![(0.,360.,true)],
+        vec![(0.,100.,true)],
+        vec![(10.,20.,true),(60.,70.,true)],
+    ]);
+    assert_eq!(params.extra_intervals[2].len(), 1, "Second lightness band should be an extra interval");
+    let mask = img.threshold_img_with_progress(&params, (255,0,0), OutputMode::Mask, |_| (), || false).expect("Couldn't threshold").expect("Thresholding was cancelled");
+    let got: Vec<u8> = mask.to_luma8().pixels().map(|p| p.0[0]).collect();
+    assert_eq!(got, [255,0,255,0], "Only pixels in either lightness band should pass");
+}
+
+#[test]
+pub fn intervals_round_trip() {
+    let intervals = [vec![(200.,20.,true),(100.,120.,false)], vec![], vec![(0.,50.,true)]];
+    let params = ThreshParams::from_native_intervals(ColorSpace::HSBorHSV, WhitePoint::D65, intervals.clone());
+    assert_eq!(params.depths()[1], (0.,255.,true), "A depth without intervals should let everything through");
+    for (got, expected) in params.native_intervals().into_iter().zip(intervals) {
+        let expected = match expected.is_empty() {
+            true => vec![(0.,100.,true)],
+            false => expected,
+        };
+        assert_eq!(got.len(), expected.len(), "Wrong number of intervals after round trip");
+        for (got, expected) in got.into_iter().zip(expected) {
+            assert!((got.0 - expected.0).abs() < 0.001 && (got.1 - expected.1).abs() < 0.001 && got.2 == expected.2,
+                "Interval {:?} changed to {:?} after round trip", expected, got);
+        }//end checking each interval
+    }//end checking each depth
+}
+
+#[test]
+pub fn exporters_reject_extra_intervals() {
+    let params = ThreshParams::from_intervals(ColorSpace::HSBorHSV, WhitePoint::D65, [
+        vec![(0.,10.,true),(240.,255.,true)], vec![], vec![],
+    ]);
+    assert!(params.to_imagej_macro().is_err(), "ImageJ can't hold two ranges for one channel");
+    assert!(params.to_opencv_bounds().is_err(), "OpenCV can't hold two ranges for one channel");
+}
+
+#[test]
+pub fn expression_precedence() {
+    let expr = ThreshExpr::parse("hue in 20..40 AND (saturation > 40 or brightness < 20)", ColorSpace::HSBorHSV, WhitePoint::D65)
+        .expect("Couldn't parse expression");
+    let hsb = |h: f32, s: f32, b: f32| [h * 255. / 360., s * 2.55, b * 2.55];
+    assert!(expr.matches(&|_| hsb(30., 50., 50.)), "Hue 30 with saturation 50 should pass");
+    assert!(expr.matches(&|_| hsb(30., 10., 10.)), "Hue 30 with brightness 10 should pass");
+    assert!(!expr.matches(&|_| hsb(30., 10., 50.)), "Hue 30 with low saturation and high brightness should fail");
+    assert!(!expr.matches(&|_| hsb(60., 50., 50.)), "Hue 60 should fail");
+    // without parentheses, and binds tighter than or
+    let expr = ThreshExpr::parse("hue in 20..40 and saturation > 40 or brightness < 20", ColorSpace::HSBorHSV, WhitePoint::D65)
+        .expect("Couldn't parse expression");
+    assert!(expr.matches(&|_| hsb(60., 10., 10.)), "Brightness 10 should pass on its own");
+}
+
+#[test]
+pub fn expression_not_and_hue_wrap() {
+    let expr = ThreshExpr::parse("not d1 in 240..20 and value >= 50", ColorSpace::HSBorHSV, WhitePoint::D65)
+        .expect("Couldn't parse expression");
+    assert!(!expr.matches(&|_| [0., 255., 255.]), "Red is inside the wrapped hue range");
+    assert!(expr.matches(&|_| [85., 255., 255.]), "Green is outside the wrapped hue range");
+    assert!(!expr.matches(&|_| [85., 255., 100.]), "Brightness below 50 percent should fail");
+}
+
+#[test]
+pub fn expression_errors() {
+    for (text, reason) in [
+        ("hue in 20..", "missing max"),
+        ("hue in 20..40 and", "dangling and"),
+        ("(hue < 20", "unclosed parenthesis"),
+        ("hue = 20", "unsupported operator"),
+        ("redness > 5", "unknown channel"),
+        ("hue > 20 saturation > 5", "missing operator"),
+    ] {
+        assert!(ThreshExpr::parse(text, ColorSpace::HSBorHSV, WhitePoint::D65).is_err(), "Expected \"{text}\" to fail with {reason}");
+    }//end checking each bad expression
+    assert!(ThreshExpr::parse("y > 0.5", ColorSpace::YXY, WhitePoint::D65).is_ok(), "xyY's y channel should win over the y of Luminance Y");
+}
+
+#[test]
+pub fn depths_expression_matches_sliders() {
+    let params = ThreshParams::from_native_intervals(ColorSpace::HSBorHSV, WhitePoint::D65, [
+        vec![(0.,15.,true),(230.,20.,false)],
+        vec![(10.,100.,true)],
+        vec![(80.,20.,false)],
+    ]);
+    let text = params.depths_expr().to_text(params.color_space, params.white_point);
+    assert_eq!(text, "(hue in 0..15 or not hue in 230..20) and saturation in 10..100 and brightness in 20..80", "Unexpected text for the depths");
+    let parsed = ThreshExpr::parse(&text, params.color_space, params.white_point).expect("Couldn't parse text of the depths");
+    let rgba = RgbaImage::from_fn(64, 48, |x, y| image::Rgba([(x * 4) as u8, (y * 5) as u8, ((x + y) * 2) as u8, 255]));
+    let img = ImageToProc::from_image(DynamicImage::ImageRgba8(rgba));
+    let from_depths = img.threshold_img_with_progress(&params, (255,0,0), OutputMode::Mask, |_| (), || false).expect("Couldn't threshold").expect("Thresholding was cancelled");
+    let with_text = ThreshParams { expression: Some(text.clone()), ..params.clone() };
+    let from_text = img.threshold_img_with_progress(&with_text, (255,0,0), OutputMode::Mask, |_| (), || false).expect("Couldn't threshold").expect("Thresholding was cancelled");
+    assert_eq!(from_depths, from_text, "Expression text {text} doesn't threshold the same as the depths");
+    assert_eq!(parsed.to_text(params.color_space, params.white_point), text, "Text changed after round trip");
+}
+
+#[test]
+pub fn depths_pass_matches_depths_expr() {
+    let params = ThreshParams::from_intervals(ColorSpace::HSL, WhitePoint::D65, [
+        vec![(200.,30.,true),(90.,120.,false)],
+        vec![(20.,60.,false),(100.,255.,true)],
+        vec![(230.,40.,false)],
+    ]);
+    let expr = params.depths_expr();
+    for r in (0..=255).step_by(15) {
+        for g in (0..=255).step_by(17) {
+            for b in (0..=255).step_by(51) {
+                let values = process::convert_from_rgb_f32([r,g,b], ColorSpace::HSL, WhitePoint::D65);
+                assert_eq!(params.depths_pass(values), expr.matches(&|_| values), "Depths and their expression differ for {:?}", [r,g,b]);
+            }//end checking each blue value
+        }//end checking each green value
+    }//end checking each red value
+}
+
+#[test]
+pub fn expression_replaces_depths() {
+    let img = ImageToProc::from_image(DynamicImage::ImageRgb8(image::RgbImage::from_fn(2, 1, |x, _| match x {
+        0 => image::Rgb([200,30,30]),
+        _ => image::Rgb([30,30,200]),
+    })));
+    let params = ThreshParams {
+        expression: Some("red > 100 or blue < 100".to_string()),
+        ..ThreshParams::from_depths(ColorSpace::RGB, WhitePoint::D65, [(0.,0.,true); 3])
+    };
+    let mask = img.threshold_img_with_progress(&params, (255,0,0), OutputMode::Mask, |_| (), || false).expect("Couldn't threshold").expect("Thresholding was cancelled");
+    let got: Vec<u8> = mask.to_luma8().pixels().map(|p| p.0[0]).collect();
+    assert_eq!(got, [255,0], "Expression should be used instead of the depths");
+    let toml = ThreshPreset::new(params.clone(), (0,0,0)).to_toml().expect("Couldn't format preset as toml");
+    assert_eq!(ThreshPreset::from_toml(&toml).expect("Couldn't parse preset").params, params, "Expression changed after round trip through toml");
+}
+
+#[test]
+pub fn expression_across_color_spaces() {
+    let expr = ThreshExpr::parse("hue in 20..40 and lab.l > 50 and ycbcr601.cr < 200", ColorSpace::HSBorHSV, WhitePoint::D65)
+        .expect("Couldn't parse expression");
+    assert_eq!(expr.spaces(), vec![ColorSpace::HSBorHSV, ColorSpace::LabCIE, ColorSpace::YCbCr601], "Unexpected color spaces used");
+    let values = |hue_deg: f32, l: f32| move |space: ColorSpace| match space {
+        ColorSpace::HSBorHSV => [hue_deg * 255. / 360., 0., 0.],
+        ColorSpace::LabCIE => [l * 2.55, 0., 0.],
+        _ => [0., 0., 0.],
+    };
+    assert!(expr.matches(&values(30., 60.)), "Hue 30 with lightness 60 should pass");
+    assert!(!expr.matches(&values(30., 40.)), "Lightness 40 should fail");
+    assert!(!expr.matches(&values(60., 60.)), "Hue 60 should fail");
+    let text = expr.to_text(ColorSpace::HSBorHSV, WhitePoint::D65);
+    assert_eq!(text, "hue in 20..40 and lab.l > 50 and ycbcr601.cr < 200", "Unexpected text for the expression");
+    assert!(ThreshExpr::parse("nospace.l > 50", ColorSpace::HSBorHSV, WhitePoint::D65).is_err(), "Unknown color space should fail");
+    assert!(ThreshExpr::parse("lab.hue > 50", ColorSpace::HSBorHSV, WhitePoint::D65).is_err(), "Lab has no hue channel");
+}
+
+#[test]
+pub fn rules_are_anded_across_color_spaces() {
+    let img = ImageToProc::from_image(DynamicImage::ImageRgb8(image::RgbImage::from_fn(3, 1, |x, _| match x {
+        0 => image::Rgb([220,40,40]),
+        1 => image::Rgb([90,10,10]),
+        _ => image::Rgb([40,40,220]),
+    })));
+    let base = ThreshParams::from_native_intervals(ColorSpace::HSBorHSV, WhitePoint::D65, [
+        vec![(330.,30.,true)], vec![(0.,100.,true)], vec![(0.,100.,true)],
+    ]);
+    let params = ThreshParams {
+        rules: vec![ThreshRule::from_native(ColorSpace::LabCIE, 0, WhitePoint::D65, (40.,100.,true))],
+        ..base.clone()
+    };
+    let mask = img.threshold_img_with_progress(&params, (255,0,0), OutputMode::Mask, |_| (), || false).expect("Couldn't threshold").expect("Thresholding was cancelled");
+    let got: Vec<u8> = mask.to_luma8().pixels().map(|p| p.0[0]).collect();
+    assert_eq!(got, [255,0,0], "Only the light red should pass both the hue and the lightness rule");
+    let overlay = img.threshold_img_with_progress(&params, (10,200,30), OutputMode::Overlay, |_| (), || false).expect("Couldn't threshold").expect("Thresholding was cancelled");
+    assert_eq!(overlay, img.threshold_img_reference(&params, (10,200,30)).expect("Couldn't threshold with the reference"), "Reference thresholding should follow the rules too");
+    assert_eq!(params.to_expr().expect("Couldn't build expression").spaces(), vec![ColorSpace::HSBorHSV, ColorSpace::LabCIE], "Only the used color spaces should be converted");
+    assert!(params.to_imagej_macro().is_err(), "ImageJ can't follow rules in other color spaces");
+    assert!(params.to_opencv_bounds().is_err(), "OpenCV can't follow rules in other color spaces");
+    let toml = ThreshPreset::new(params.clone(), (0,0,0)).to_toml().expect("Couldn't format preset as toml");
+    assert_eq!(ThreshPreset::from_toml(&toml).expect("Couldn't parse preset").params, params, "Rules changed after round trip through toml");
+    let bad = ThreshParams { rules: vec![ThreshRule { color_space: ColorSpace::RGB, channel: 3, min: 0., max: 255., pass: true }], ..base };
+    assert!(bad.to_expr().is_err(), "Channel 3 doesn't exist");
+}
+
+#[test]
+pub fn invalid_params_give_errors() {
+    let img = ImageToProc::from_image(DynamicImage::ImageRgb8(image::RgbImage::from_pixel(2, 2, image::Rgb([200,30,30]))));
+    let base = ThreshParams::from_depths(ColorSpace::RGB, WhitePoint::D65, [(0.,255.,true); 3]);
+    let bad_expr = ThreshParams { expression: Some("redness > 5".to_string()), ..base.clone() };
+    let bad_rule = ThreshParams { rules: vec![ThreshRule { color_space: ColorSpace::RGB, channel: 3, min: 0., max: 255., pass: true }], ..base };
+    for bad in [bad_expr, bad_rule] {
+        assert!(img.threshold_img_with_progress(&bad, (0,0,0), OutputMode::Mask, |_| (), || false).is_err(), "Thresholding should fail for {:?}", bad);
+        assert!(img.threshold_img_with_lut(&bad, (0,0,0), OutputMode::Mask, &ConversionLut::new_for_reds(ColorSpace::RGB, WhitePoint::D65, &[]), |_| (), || false).is_err(), "Thresholding with lookup table should fail for {:?}", bad);
+        assert!(img.threshold_img_reference(&bad, (0,0,0)).is_err(), "Reference thresholding should fail for {:?}", bad);
+        assert!(bad.check_pixel([200,30,30]).is_err(), "Checking a pixel should fail for {:?}", bad);
+        let toml = ThreshPreset::new(bad.clone(), (0,0,0)).to_toml().expect("Couldn't format preset as toml");
+        assert!(ThreshPreset::from_toml(&toml).is_err(), "Loading a preset should fail for {:?}", bad);
+    }//end checking each kind of bad params
+}
+
+#[test]
+pub fn samples_set_depths_with_padding() {
+    let pixels = [[10,100,200],[30,120,220]];
+    let params = ThreshParams::from_samples(ColorSpace::RGB, WhitePoint::D65, &pixels, SamplePadding::Percent(0.)).expect("Couldn't sample pixels");
+    assert_eq!(params.depths(), [(10.,30.,true),(100.,120.,true),(200.,220.,true)], "Depths should span the samples");
+    let params = ThreshParams::from_samples(ColorSpace::RGB, WhitePoint::D65, &pixels, SamplePadding::Percent(10.)).expect("Couldn't sample pixels");
+    assert_eq!(params.depths(), [(0.,55.5,true),(74.5,145.5,true),(174.5,245.5,true)], "Depths should be padded by a tenth of the range and clamped");
+    let params = ThreshParams::from_samples(ColorSpace::RGB, WhitePoint::D65, &pixels, SamplePadding::StdDevs(2.)).expect("Couldn't sample pixels");
+    assert_eq!(params.depths(), [(0.,50.,true),(80.,140.,true),(180.,240.,true)], "Depths should be padded by two standard deviations");
+    assert!(ThreshParams::from_samples(ColorSpace::RGB, WhitePoint::D65, &[], SamplePadding::Percent(0.)).is_none(), "No samples should give no params");
+}
+
+#[test]
+pub fn samples_wrap_hue_through_zero() {
+    // hues of about 350 and 10 degrees
+    let pixels = [[255,0,43],[255,43,0]];
+    let params = ThreshParams::from_samples(ColorSpace::HSBorHSV, WhitePoint::D65, &pixels, SamplePadding::Percent(0.)).expect("Couldn't sample pixels");
+    let (min, max, pass) = params.native_depths()[0];
+    assert!(pass && min > 345. && max < 15., "Hue should wrap from about 350 to 10, not be {min}..{max}");
+    let img = ImageToProc::from_image(DynamicImage::ImageRgb8(image::RgbImage::from_fn(2, 1, |x, _| match x {
+        0 => image::Rgb([255,0,0]),
+        _ => image::Rgb([0,255,0]),
+    })));
+    let mask = img.threshold_img_with_progress(&params, (255,0,0), OutputMode::Mask, |_| (), || false).expect("Couldn't threshold").expect("Thresholding was cancelled");
+    let got: Vec<u8> = mask.to_luma8().pixels().map(|p| p.0[0]).collect();
+    assert_eq!(got, [255,0], "Red should pass between the sampled hues, while green shouldn't");
+}
+
+#[test]
+pub fn sample_pixels_in_regions() {
+    let img = ImageToProc::from_image(DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 4, |x, y| {
+        image::Rgba([(x * 10) as u8, (y * 10) as u8, 0, if (x, y) == (0, 3) {0} else {255}])
+    })));
+    // picked on a copy half the size, so each picked pixel covers four of ours
+    assert_eq!(img.sample_pixels(&SampleRegion::Point(1,0), (2,2)), [[30,10,0]], "Point should sample the middle of the pixel it covers");
+    assert_eq!(img.sample_pixels(&SampleRegion::Rect((1,1),(0,0)), (2,2)).len(), 15, "Rect should sample everything but the transparent pixel");
+    assert_eq!(img.sample_pixels(&SampleRegion::Rect((0,0),(0,1)), (4,4)), [[0,0,0],[0,10,0]], "Rect should sample its column");
+    let img = ImageToProc::from_image(DynamicImage::ImageRgb8(image::RgbImage::from_fn(5, 5, |x, y| image::Rgb([(x * 10) as u8, (y * 10) as u8, 0]))));
+    // corners land at 0.625 and 3.125, so only the center of pixel (1,1) is inside
+    assert_eq!(img.sample_pixels(&SampleRegion::Lasso(vec![(0,0),(2,0),(0,2)]), (4,4)), [[10,10,0]], "Lasso should only sample inside its polygon");
+    assert!(img.sample_pixels(&SampleRegion::Lasso(vec![(0,0),(2,2)]), (4,4)).is_empty(), "Lasso needs at least three points");
+}
+
+#[test]
+pub fn check_pixel_reports_each_test() {
+    let params = ThreshParams {
+        rules: vec![
+            ThreshRule::from_native(ColorSpace::LabCIE, 0, WhitePoint::D65, (40.,100.,true)),
+            ThreshRule::from_native(ColorSpace::RGB, 2, WhitePoint::D65, (0.,100.,true)),
+        ],
+        ..ThreshParams::from_native_intervals(ColorSpace::HSBorHSV, WhitePoint::D65, [
+            vec![(330.,30.,true)], vec![(50.,100.,true)], vec![(0.,40.,true),(60.,100.,true)],
+        ])
+    };
+    assert_eq!(params.check_pixel([220,40,40]).expect("Params are valid"), PixelCheck { depths: [true,true,true], rules: vec![true,true], passes: true }, "Light red should pass everything");
+    assert_eq!(params.check_pixel([120,20,20]).expect("Params are valid"), PixelCheck { depths: [true,true,false], rules: vec![false,true], passes: false }, "Dark red should fail its brightness and lightness");
+    assert_eq!(params.check_pixel([40,40,220]).expect("Params are valid"), PixelCheck { depths: [false,true,true], rules: vec![false,false], passes: false }, "Blue should fail its hue, lightness and blue rule");
+    let with_expr = ThreshParams { expression: Some("rgb.blue > 200".to_string()), rules: Vec::new(), ..params };
+    assert!(with_expr.check_pixel([40,40,220]).expect("Params are valid").passes, "The expression should be followed instead of the depths");
+}
+
+#[test]
+pub fn scale_point_maps_to_our_pixels() {
+    let img = ImageToProc::from_image(DynamicImage::ImageRgb8(image::RgbImage::from_fn(9, 6, |x, y| image::Rgb([x as u8, y as u8, 0]))));
+    assert_eq!(img.scale_point((0,0), (3,2)), Some((1,1)), "Top left should map to the middle of the pixels it covers");
+    assert_eq!(img.scale_point((2,1), (3,2)), Some((7,4)), "Bottom right should map to the middle of the pixels it covers");
+    assert_eq!(img.scale_point((3,0), (3,2)), None, "Points off the picked image should give None");
+    assert_eq!(img.rgb_at((7,4)), Some([7,4,0]), "Unexpected rgb");
+    assert_eq!(img.rgb_at((9,0)), None, "Pixels off the image should give None");
+}
+
+#[test]
+pub fn channel_histograms_count_each_channel() {
+    let img = ImageToProc::from_image(DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 2, |x, y| {
+        image::Rgba([(x * 80) as u8, 255, (y * 255) as u8, 128])
+    })));
+    let [red, green, blue] = img.channel_histograms(ColorSpace::RGB, WhitePoint::D65, 4);
+    assert_eq!(red, [2,2,2,2], "Reds of 0, 80, 160 and 240 should each land in their own bin");
+    assert_eq!(green, [0,0,0,8], "Full green should land in the last bin");
+    assert_eq!(blue, [4,0,0,4], "Blue should split between the first and last bins");
+    let hue = &img.channel_histograms(ColorSpace::HSBorHSV, WhitePoint::D65, 16)[0];
+    assert_eq!(hue.iter().sum::<u32>(), 8, "Every pixel should be counted once");
+    assert_eq!(img.channel_histograms(ColorSpace::RGB, WhitePoint::D65, 0), [vec![], vec![], vec![]], "No bins should give no counts");
+}
@@ -0,0 +1,106 @@
+use std::sync::{atomic::{AtomicU64, Ordering}, mpsc, Arc};
+use std::thread;
+
+use color_space_threshold_helper::{enums::{InterfaceMessage, OutputMode}, process::{ConversionLut, ImageToProc, ThreshParams}};
+use fltk::app::Sender;
+use image::{DynamicImage, GenericImageView};
+
+/// Full images with at least this many pixels are thresholded with a
+/// cached lookup table. Building the table converts all 2^24 colors,
+/// about as much work as converting an image this big, so it only pays
+/// off once big images get re-thresholded a few times.
+const LUT_MIN_PIXELS: u64 = 16_000_000;
+
+/// The finished image from a threshold job
+pub struct ThresholdResult {
+    pub job_id: u64,
+    /// Whether this was a downscaled live preview rather than the full image
+    pub is_preview: bool,
+    pub img: DynamicImage,
+}//end struct ThresholdResult
+
+/// Runs thresholding on a background thread, so the window stays
+/// responsive on large images. Starting a new job cancels the
+/// previous one, and progress and completion are reported as
+/// InterfaceMessages over the gui's channel.
+pub struct ThresholdWorker {
+    msg_sender: Sender<InterfaceMessage>,
+    latest_job: Arc<AtomicU64>,
+    result_sender: mpsc::Sender<ThresholdResult>,
+    result_receiver: mpsc::Receiver<ThresholdResult>,
+}//end struct ThresholdWorker
+
+impl ThresholdWorker {
+    pub fn new(msg_sender: Sender<InterfaceMessage>) -> ThresholdWorker {
+        let (result_sender, result_receiver) = mpsc::channel();
+        ThresholdWorker {
+            msg_sender,
+            latest_job: Arc::new(AtomicU64::new(0)),
+            result_sender,
+            result_receiver,
+        }//end struct construction
+    }//end new()
+
+    /// Gets the id of the job that is currently wanted
+    pub fn latest_job(&self) -> u64 {
+        self.latest_job.load(Ordering::SeqCst)
+    }//end latest_job()
+
+    /// Cancels whatever job is running without starting a new one
+    pub fn cancel(&self) -> u64 {
+        self.latest_job.fetch_add(1, Ordering::SeqCst) + 1
+    }//end cancel()
+
+    /// Starts thresholding img on a new thread, superseding any job
+    /// already running, and returns the id of the new job.
+    pub fn start(
+        &self,
+        img: Arc<ImageToProc>,
+        params: ThreshParams,
+        thresh_color: (u8,u8,u8),
+        mode: OutputMode,
+        is_preview: bool,
+    ) -> u64 {
+        let job_id = self.cancel();
+        let latest_job = self.latest_job.clone();
+        let msg_sender = self.msg_sender;
+        let result_sender = self.result_sender.clone();
+        thread::spawn(move || {
+            let last_percent = AtomicU64::new(0);
+            let progress = |fraction: f32| {
+                let percent = (fraction * 100.) as u64;
+                if last_percent.swap(percent, Ordering::Relaxed) != percent {
+                    msg_sender.send(InterfaceMessage::ThresholdProgress(job_id, percent as u8));
+                }//end if we have a new percent to report
+            };
+            let cancelled = || latest_job.load(Ordering::SeqCst) != job_id;
+            let (width, height) = img.get_image().dimensions();
+            let th_img = match !is_preview && width as u64 * height as u64 >= LUT_MIN_PIXELS {
+                true => match ConversionLut::cached(params.color_space, params.white_point, cancelled) {
+                    Some(lut) => img.threshold_img_with_lut(&params, thresh_color, mode, &lut, progress, cancelled),
+                    None => Ok(None),
+                },
+                false => img.threshold_img_with_progress(&params, thresh_color, mode, progress, cancelled),
+            };
+            match th_img {
+                Err(err) => println!("Couldn't threshold image because: {err}"),
+                Ok(None) => (),
+                Ok(Some(img)) => {
+                    // only fails once the worker is dropped, at which point nobody wants the result
+                    if result_sender.send(ThresholdResult { job_id, is_preview, img }).is_ok() {
+                        msg_sender.send(InterfaceMessage::ThresholdDone(job_id));
+                    }//end if we could hand off the result
+                },
+            }//end matching whether the job finished
+        });
+        job_id
+    }//end start()
+
+    /// Takes the result of the job given if it finished and is still
+    /// the latest job, throwing away any stale results along the way.
+    pub fn take_result(&self, job_id: u64) -> Option<ThresholdResult> {
+        if job_id != self.latest_job() {return None;}
+        self.result_receiver.try_iter()
+            .find(|result| result.job_id == job_id)
+    }//end take_result()
+}//end impl for ThresholdWorker